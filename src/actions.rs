@@ -0,0 +1,841 @@
+//! Changes to the table that are shared by the subcommands and `kit shell`.
+//!
+//! Each action resolves the names it is given, changes a copy of the table,
+//! saves it in a single write and runs the hooks. Nothing is saved if any of
+//! the changes fails. How errors are reported is left to the caller.
+
+use clap::ArgMatches;
+use log::info;
+use std::error;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+use crate::cli::FIELDS;
+use crate::edit;
+use crate::helpers::{
+    confirm, get_hooks, get_list, get_sync_state_path, resolve_name, resolve_name_strictly,
+};
+use crate::hooks;
+use crate::journal::{self, Record};
+use crate::names::lookup_group;
+use crate::parse::{parse_date, parse_interval};
+use crate::sync::{self, SyncState};
+use crate::table::{Entry, ExistingEntry, Group, Interaction, MissingGroup, Table};
+use crate::wizard::{Given, MissingDetails, Prompter};
+
+#[derive(Debug, Clone)]
+pub struct NoTaggedEntries {
+    tag: String,
+}
+
+impl fmt::Display for NoTaggedEntries {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "No entry with the tag {:?} can be changed.", self.tag)
+    }
+}
+
+impl error::Error for NoTaggedEntries {
+    fn description(&self) -> &str {
+        "No tagged entries"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Unconfirmed {
+    count: usize,
+}
+
+impl fmt::Display for Unconfirmed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "This would change {} entries, pass `--yes` to do so without a terminal.",
+            self.count
+        )
+    }
+}
+
+impl error::Error for Unconfirmed {
+    fn description(&self) -> &str {
+        "Unconfirmed change of several entries"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnknownField {
+    field: String,
+}
+
+impl fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Unknown field {:?}, expected one of {}.",
+            self.field,
+            FIELDS.join(", ")
+        )
+    }
+}
+
+impl error::Error for UnknownField {
+    fn description(&self) -> &str {
+        "Unknown field"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnknownGroupCommand {
+    command: String,
+}
+
+impl fmt::Display for UnknownGroupCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Unknown group command {:?}, expected one of create, delete, add, remove, list.",
+            self.command
+        )
+    }
+}
+
+impl error::Error for UnknownGroupCommand {
+    fn description(&self) -> &str {
+        "Unknown group command"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportFailed {
+    path: String,
+    message: String,
+}
+
+impl fmt::Display for ImportFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Importing {:?} failed: {}", self.path, self.message)
+    }
+}
+
+impl error::Error for ImportFailed {
+    fn description(&self) -> &str {
+        "Import failed"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupName {
+    name: String,
+}
+
+impl fmt::Display for GroupName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} is a group, use `kit group` to change it or name its members.",
+            self.name
+        )
+    }
+}
+
+impl error::Error for GroupName {
+    fn description(&self) -> &str {
+        "Group instead of entry"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+/// Resolves the name of an entry to change, rejecting the names of groups so
+/// that a group isn't mistaken for a similar entry. Uncertain matches have to
+/// be confirmed unless `yes` is given.
+fn entry_name(data: &Table, raw: &str, yes: bool) -> Result<String, Box<dyn Error>> {
+    if !data.entries.contains_key(raw) {
+        if let Some(group) = lookup_group(data, raw) {
+            return Err(Box::new(GroupName { name: group }));
+        }
+    }
+    resolve_name_strictly(data, raw, yes)
+}
+
+/// The entries given by name, followed by those with the tag for which
+/// `applies` is true. Names given twice are only changed once.
+fn targets<'a>(
+    data: &Table,
+    raw_names: impl Iterator<Item = &'a str>,
+    tag: Option<&str>,
+    yes: bool,
+    applies: impl Fn(&str) -> bool,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut names = Vec::new();
+    for raw in raw_names {
+        let name = entry_name(data, raw, yes)?;
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    if let Some(tag) = tag {
+        let mut tagged: Vec<String> = data
+            .entries
+            .values()
+            .filter(|e| e.has_tag(tag) && applies(&e.name) && !names.contains(&e.name))
+            .map(|e| e.name.clone())
+            .collect();
+        if tagged.is_empty() && names.is_empty() {
+            return Err(Box::new(NoTaggedEntries {
+                tag: tag.to_string(),
+            }));
+        }
+        tagged.sort();
+        names.extend(tagged);
+    }
+    Ok(names)
+}
+
+/// Lists the entries and asks before changing more than one of them,
+/// unless `--yes` is given.
+fn confirmed(c: &ArgMatches, action: &str, names: &[String]) -> Result<bool, Unconfirmed> {
+    if names.len() < 2 || c.is_present("yes") {
+        return Ok(true);
+    }
+    if !io::stdin().is_terminal() {
+        return Err(Unconfirmed { count: names.len() });
+    }
+    println!("{} {} entries:", action, names.len());
+    for name in names {
+        println!("  {}", name);
+    }
+    let confirmed = confirm("Continue?");
+    if !confirmed {
+        info!("Nothing changed.");
+    }
+    Ok(confirmed)
+}
+
+/// Replaces the table by its changed copy and saves it.
+fn commit(data: &mut Table, changed: Table, table_path: &Path) {
+    *data = changed;
+    data.to_json(table_path);
+}
+
+/// Like `commit`, but a change of several entries is written to the journal
+/// first. Nothing is saved if that fails.
+fn commit_changes(
+    data: &mut Table,
+    changed: Table,
+    table_path: &Path,
+    command: &str,
+    names: &[String],
+) -> io::Result<()> {
+    if names.len() > 1 {
+        journal::append(table_path, &Record::new(command, names, data))?;
+    }
+    commit(data, changed, table_path);
+    Ok(())
+}
+
+pub fn just_talked_to(
+    data: &mut Table,
+    table_path: &Path,
+    c: &ArgMatches,
+) -> Result<(), Box<dyn Error>> {
+    // a group stands for all of its members
+    let given = c
+        .values_of("name")
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    let mut raw_names = Vec::new();
+    let mut groups = 0;
+    for raw in &given {
+        match lookup_group(data, raw) {
+            Some(group) => {
+                groups += 1;
+                raw_names.extend(data.groups[&group].members.iter().map(|m| m.as_str()));
+            }
+            None => raw_names.push(raw),
+        }
+    }
+    let names = targets(
+        data,
+        raw_names.into_iter(),
+        c.value_of("tag"),
+        c.is_present("yes"),
+        |_| true,
+    )?;
+    // naming a single group is as deliberate as naming a single entry
+    let one_group = groups == 1 && given.len() == 1 && c.value_of("tag").is_none();
+    if !one_group && !confirmed(c, "Log a contact with", &names)? {
+        return Ok(());
+    }
+    let interaction = Interaction {
+        date: parse_date("now")?,
+        channel: "manual".to_string(),
+    };
+    let mut changed = data.clone();
+    for name in &names {
+        changed
+            .entries
+            .get_mut(name)
+            .unwrap()
+            .record_interaction(interaction.clone());
+    }
+    commit_changes(data, changed, table_path, "just-talked-to", &names)?;
+    let hooks = get_hooks();
+    for name in &names {
+        info!("Modified {:?}.", name);
+        hooks::run(
+            &hooks,
+            "interaction.logged",
+            &data.entries[name],
+            Some(&interaction),
+        );
+    }
+    Ok(())
+}
+
+/// Adds an entry, asking for everything that is missing when run in a terminal.
+pub fn add(data: &mut Table, table_path: &Path, c: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let given = Given {
+        name: c.value_of("name"),
+        interval: c.value_of("interval").map(parse_interval).transpose()?,
+        last_chat: c.value_of("last chat"),
+    };
+    let entry = match (given.name, given.interval, given.last_chat) {
+        (Some(name), Some(interval), Some(last_chat)) if !c.is_present("interactive") => {
+            Entry::new(name.to_string(), interval, parse_date(last_chat)?)
+        }
+        _ if !io::stdin().is_terminal() => return Err(Box::new(MissingDetails)),
+        _ => {
+            if let Some(name) = given.name {
+                if data.entries.contains_key(name) {
+                    return Err(Box::new(ExistingEntry {
+                        name: name.to_string(),
+                    }));
+                }
+            }
+            let stdin = io::stdin();
+            match Prompter::new(stdin.lock(), io::stdout()).ask_entry(data, given)? {
+                Some(entry) => entry,
+                None => {
+                    info!("Nothing added.");
+                    return Ok(());
+                }
+            }
+        }
+    };
+    let name = entry.name.clone();
+    data.add_entry(entry)?;
+    data.to_json(table_path);
+    info!("Added {:?}.", name);
+    hooks::run(&get_hooks(), "entry.added", &data.entries[&name], None);
+    Ok(())
+}
+
+pub fn remove(data: &mut Table, table_path: &Path, c: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let names = targets(
+        data,
+        c.values_of("name").into_iter().flatten(),
+        c.value_of("tag"),
+        c.is_present("yes"),
+        |_| true,
+    )?;
+    if !confirmed(c, "Remove", &names)? {
+        return Ok(());
+    }
+    let mut changed = data.clone();
+    let mut removed = Vec::new();
+    for name in &names {
+        removed.push(changed.entries[name].clone());
+        changed.remove_entry(name.to_string())?;
+    }
+    commit_changes(data, changed, table_path, "remove", &names)?;
+    let hooks = get_hooks();
+    for entry in &removed {
+        info!("Removed {:?}.", entry.name);
+        hooks::run(&hooks, "entry.removed", entry, None);
+    }
+    Ok(())
+}
+
+/// Sets `field` of the entry `name` to the parsed `raw_new_val`.
+fn modify_entry(
+    data: &mut Table,
+    name: &str,
+    field: &str,
+    raw_new_val: &str,
+) -> Result<(), Box<dyn Error>> {
+    let entry = data.entries.get_mut(name).unwrap();
+    match field {
+        "name" => data.rename_entry(name.to_string(), raw_new_val.to_string())?,
+        "interval" => {
+            entry.interval = parse_interval(raw_new_val)?;
+            entry.update_remaining_time();
+        }
+        "last" => {
+            entry.last_contact = parse_date(raw_new_val)?;
+            entry.update_remaining_time();
+        }
+        "aliases" => entry.aliases = get_list(raw_new_val),
+        "emails" => entry.emails = get_list(raw_new_val),
+        "phones" => entry.phones = get_list(raw_new_val),
+        "birthday" => {
+            entry.birthday = match raw_new_val {
+                "none" => None,
+                raw => Some(parse_date(raw)?.date_naive()),
+            };
+        }
+        "tags" => entry.tags = get_list(raw_new_val),
+        "notes" => {
+            entry.notes = match raw_new_val {
+                "none" => None,
+                raw => Some(raw.to_string()),
+            };
+        }
+        other => {
+            return Err(Box::new(UnknownField {
+                field: other.to_string(),
+            }))
+        }
+    }
+    Ok(())
+}
+
+pub fn modify(data: &mut Table, table_path: &Path, c: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let (tag, field, raw_new_val) = match c.values_of("tag") {
+        Some(values) => {
+            let values = values.collect::<Vec<&str>>();
+            (Some(values[0]), values[1], values[2])
+        }
+        None => (
+            None,
+            c.value_of("field").unwrap(),
+            c.value_of("new value").unwrap(),
+        ),
+    };
+    // clap only checks the field that isn't given to `--tag`
+    if !FIELDS.contains(&field) {
+        return Err(Box::new(UnknownField {
+            field: field.to_string(),
+        }));
+    }
+    let names = targets(
+        data,
+        c.value_of("name").into_iter(),
+        tag,
+        c.is_present("yes"),
+        |_| true,
+    )?;
+    if !confirmed(c, &format!("Set the {} of", field), &names)? {
+        return Ok(());
+    }
+    let mut changed = data.clone();
+    for name in &names {
+        modify_entry(&mut changed, name, field, raw_new_val)?;
+    }
+    commit_changes(
+        data,
+        changed,
+        table_path,
+        &format!("modify {}", field),
+        &names,
+    )?;
+    let hooks = get_hooks();
+    for name in &names {
+        info!("Modified {:?}.", name);
+        let new_name = if field == "name" { raw_new_val } else { name };
+        hooks::run(&hooks, "entry.modified", &data.entries[new_name], None);
+    }
+    Ok(())
+}
+
+/// Opens the entry given by name, or all entries, in an editor and saves the changes.
+pub fn edit(data: &mut Table, table_path: &Path, c: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let mut names: Vec<String> = match c.value_of("name") {
+        Some(raw) => vec![resolve_name(data, raw)?],
+        None => data.entries.keys().cloned().collect(),
+    };
+    names.sort();
+    edit::run(data, table_path, &names)
+}
+
+/// Records the contacts found in a calendar, mailbox, chat export or phone backup.
+/// What was already read is remembered in the sync state.
+pub fn sync(data: &mut Table, table_path: &Path, c: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let state_path = get_sync_state_path();
+    let mut state = SyncState::from_json(&state_path).unwrap_or_default();
+    let (source, sc) = c.subcommand();
+    let sc = sc.unwrap();
+    let path = Path::new(sc.value_of("file").unwrap());
+    let mut changed = data.clone();
+    let recorded = match source {
+        "ics" => sync::import_ics(&mut changed, path, sc.is_present("interactive")),
+        "mail" => sync::import_mail(&mut changed, path, &mut state),
+        "calls" => {
+            // checked by clap
+            let min_duration = sc.value_of("min duration").unwrap().parse()?;
+            sync::import_calls(
+                &mut changed,
+                path,
+                min_duration,
+                sc.value_of("country code"),
+            )
+        }
+        "whatsapp" | "telegram" | "signal" => {
+            let name = sc
+                .value_of("name")
+                .map(|n| resolve_name(data, n))
+                .transpose()?;
+            let interactive = sc.is_present("interactive");
+            sync::import_chat(&mut changed, path, source, name.as_deref(), interactive)
+        }
+        other => unreachable!("unknown source {}", other),
+    };
+    let recorded = recorded.map_err(|e| ImportFailed {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })?;
+    commit(data, changed, table_path);
+    state.to_json(&state_path);
+    info!(
+        "Recorded {} new interaction(s) from {:?}.",
+        recorded.len(),
+        path
+    );
+    let hooks = get_hooks();
+    for (name, interaction) in &recorded {
+        hooks::run(
+            &hooks,
+            "interaction.logged",
+            &data.entries[name],
+            Some(interaction),
+        );
+    }
+    Ok(())
+}
+
+/// Creates, deletes, changes and lists groups.
+pub fn group(data: &mut Table, table_path: &Path, c: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let (command, sc) = c.subcommand();
+    let sc = sc.ok_or_else(|| UnknownGroupCommand {
+        command: command.to_string(),
+    })?;
+    if command == "list" {
+        let mut groups = data.groups.values().collect::<Vec<&Group>>();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        for group in groups {
+            println!("{}: {}", group.name, group.members.join(", "));
+        }
+        return Ok(());
+    }
+    let members = sc
+        .values_of("members")
+        .into_iter()
+        .flatten()
+        .map(|raw| entry_name(data, raw, sc.is_present("yes")))
+        .collect::<Result<Vec<String>, _>>()?;
+    let raw_group = sc.value_of("group").unwrap();
+    let mut changed = data.clone();
+    if command == "create" {
+        changed.add_group(Group {
+            name: raw_group.to_string(),
+            members,
+        })?;
+        commit(data, changed, table_path);
+        info!("Created group {:?}.", raw_group);
+        return Ok(());
+    }
+    let group = lookup_group(data, raw_group).ok_or_else(|| MissingGroup {
+        name: raw_group.to_string(),
+    })?;
+    match command {
+        "delete" => {
+            changed.remove_group(group.clone())?;
+        }
+        "add" => changed.add_members(&group, &members)?,
+        "remove" => changed.remove_members(&group, &members)?,
+        other => {
+            return Err(Box::new(UnknownGroupCommand {
+                command: other.to_string(),
+            }))
+        }
+    }
+    commit(data, changed, table_path);
+    if command == "delete" {
+        info!("Deleted group {:?}.", group);
+    } else {
+        info!("Modified group {:?}.", group);
+    }
+    Ok(())
+}
+
+pub fn resume(data: &mut Table, table_path: &Path, c: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let names = targets(
+        data,
+        c.values_of("name").into_iter().flatten(),
+        c.value_of("tag"),
+        c.is_present("yes"),
+        |n| data.suspended_entries.contains(n),
+    )?;
+    if !confirmed(c, "Resume", &names)? {
+        return Ok(());
+    }
+    let mut changed = data.clone();
+    for name in &names {
+        changed.resume_entry(name.to_string())?;
+    }
+    commit_changes(data, changed, table_path, "resume", &names)?;
+    let hooks = get_hooks();
+    for name in &names {
+        info!("Resumed {:?}.", name);
+        hooks::run(&hooks, "entry.resumed", &data.entries[name], None);
+    }
+    Ok(())
+}
+
+pub fn suspend(data: &mut Table, table_path: &Path, c: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let names = targets(
+        data,
+        c.values_of("name").into_iter().flatten(),
+        c.value_of("tag"),
+        c.is_present("yes"),
+        |n| !data.suspended_entries.contains(n),
+    )?;
+    if !confirmed(c, "Suspend", &names)? {
+        return Ok(());
+    }
+    let mut changed = data.clone();
+    for name in &names {
+        changed.suspend_entry(name.to_string())?;
+    }
+    commit_changes(data, changed, table_path, "suspend", &names)?;
+    let hooks = get_hooks();
+    for name in &names {
+        info!("Suspended {:?}.", name);
+        hooks::run(&hooks, "entry.suspended", &data.entries[name], None);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::build_cli;
+
+    fn run(data: &mut Table, path: &Path, line: &[&str]) -> Result<(), Box<dyn Error>> {
+        let args = build_cli().get_matches_from(std::iter::once("kit").chain(line.iter().cloned()));
+        let (name, c) = args.subcommand();
+        let action = match name {
+            "add" => add,
+            "modify" => modify,
+            "suspend" => suspend,
+            "just-talked-to" => just_talked_to,
+            "remove" => remove,
+            other => unimplemented!("{}", other),
+        };
+        action(data, path, c.unwrap())
+    }
+
+    #[test]
+    fn test_errors_keep_the_table() {
+        let dir = std::env::temp_dir().join(format!("kit-test-actions-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("table.json");
+        let mut data = Table::new();
+        run(&mut data, &path, &["add", "Anna", "7", "2020-1-1"]).unwrap();
+        assert!(run(&mut data, &path, &["add", "Anna", "3", "now"]).is_err());
+        let e = run(&mut data, &path, &["modify", "anna", "interval", "2w"]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            r#"Invalid interval "2w", expected a number of days"#
+        );
+        assert!(run(&mut data, &path, &["suspend", "Zoe"]).is_err());
+        run(&mut data, &path, &["suspend", "anna"]).unwrap();
+        let saved = Table::from_json(&path).unwrap();
+        assert_eq!(saved.entries["Anna"].interval, 7);
+        assert!(saved.suspended_entries.contains("Anna"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_several_entries() {
+        let dir =
+            std::env::temp_dir().join(format!("kit-test-actions-several-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("table.json");
+        let mut data = Table::new();
+        for name in &["Anna", "Ben", "Carla", "Dan"] {
+            run(&mut data, &path, &["add", name, "7", "2020-1-1"]).unwrap();
+        }
+        run(&mut data, &path, &["modify", "--tag", "work", "tags", "x"]).unwrap_err();
+        run(&mut data, &path, &["modify", "ben", "tags", "work,school"]).unwrap();
+        run(&mut data, &path, &["modify", "carla", "tags", "Work"]).unwrap();
+
+        let e = run(&mut data, &path, &["just-talked-to", "anna", "ben"]).unwrap_err();
+        assert!(e.is::<Unconfirmed>());
+        run(
+            &mut data,
+            &path,
+            &["just-talked-to", "anna", "ben", "anna", "-y"],
+        )
+        .unwrap();
+        assert_eq!(data.entries["Anna"].interactions.len(), 1);
+        assert_eq!(data.entries["Ben"].interactions.len(), 1);
+        assert!(data.entries["Carla"].interactions.is_empty());
+
+        run(
+            &mut data,
+            &path,
+            &["modify", "--tag", "work", "interval", "14", "-y"],
+        )
+        .unwrap();
+        assert_eq!(data.entries["Ben"].interval, 14);
+        assert_eq!(data.entries["Carla"].interval, 14);
+        assert_eq!(data.entries["Anna"].interval, 7);
+        let journal = std::fs::read_to_string(dir.join("journal.jsonl")).unwrap();
+        let records: Vec<Record> = journal
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].command, "just-talked-to");
+        assert_eq!(records[1].command, "modify interval");
+        assert_eq!(records[1].names, vec!["Ben", "Carla"]);
+        assert_eq!(records[1].entries[0].interval, 7);
+        let e = run(
+            &mut data,
+            &path,
+            &["modify", "--tag", "work", "colour", "red"],
+        )
+        .unwrap_err();
+        assert!(e.is::<UnknownField>());
+        // without a tag, clap checks the field
+        assert!(build_cli()
+            .get_matches_from_safe(["kit", "modify", "ben", "colour", "red"])
+            .is_err());
+
+        run(&mut data, &path, &["suspend", "ben"]).unwrap();
+        run(&mut data, &path, &["suspend", "--tag", "work"]).unwrap();
+        assert!(data.suspended_entries.contains("Carla"));
+        // one rename succeeds, the second one clashes, so nothing is saved
+        let e = run(
+            &mut data,
+            &path,
+            &["modify", "--tag", "work", "name", "Eve", "-y"],
+        )
+        .unwrap_err();
+        assert!(e.is::<ExistingEntry>());
+        assert!(data.entries.contains_key("Ben"));
+        let e = run(&mut data, &path, &["remove", "dan", "zoe", "-y"]).unwrap_err();
+        assert!(!e.is::<Unconfirmed>());
+        assert!(Table::from_json(&path).unwrap().entries.contains_key("Dan"));
+
+        run(
+            &mut data,
+            &path,
+            &["remove", "--tag", "school", "dan", "-y"],
+        )
+        .unwrap();
+        let saved = Table::from_json(&path).unwrap();
+        assert_eq!(saved.entries.len(), 2);
+        assert!(!saved.suspended_entries.contains("Ben"));
+        assert!(saved.suspended_entries.contains("Carla"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_uncertain_names() {
+        let dir =
+            std::env::temp_dir().join(format!("kit-test-actions-uncertain-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("table.json");
+        let mut data = Table::new();
+        run(&mut data, &path, &["add", "Annabelle", "7", "2020-1-1"]).unwrap();
+        // prefixes and typos have to be confirmed, which needs a terminal or `--yes`
+        let e = run(&mut data, &path, &["just-talked-to", "annab"]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Name \"annab\" is not in the list, did you mean \"Annabelle\"? \
+            Pass `--yes` to use it without a terminal."
+        );
+        assert!(run(&mut data, &path, &["modify", "anabele", "interval", "14"]).is_err());
+        assert!(run(&mut data, &path, &["suspend", "annab"]).is_err());
+        assert!(run(&mut data, &path, &["remove", "annab"]).is_err());
+        assert_eq!(data.entries["Annabelle"].interval, 7);
+        run(
+            &mut data,
+            &path,
+            &["modify", "anabele", "interval", "14", "-y"],
+        )
+        .unwrap();
+        assert_eq!(data.entries["Annabelle"].interval, 14);
+        // names that only differ in case or accents are certain
+        run(&mut data, &path, &["modify", "ANNABELLE", "name", "Bella"]).unwrap();
+        run(&mut data, &path, &["remove", "bella"]).unwrap();
+        assert!(data.entries.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_group_names() {
+        let dir =
+            std::env::temp_dir().join(format!("kit-test-actions-groups-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("table.json");
+        let mut data = Table::new();
+        run(&mut data, &path, &["add", "Anna", "7", "2020-1-1"]).unwrap();
+        run(&mut data, &path, &["add", "Bandit", "7", "2020-1-1"]).unwrap();
+        data.add_group(Group {
+            name: "Band".to_string(),
+            members: vec!["Anna".to_string(), "Bandit".to_string()],
+        })
+        .unwrap();
+        // "band" would otherwise be a prefix of "Bandit"
+        let e = run(&mut data, &path, &["suspend", "band"]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            r#""Band" is a group, use `kit group` to change it or name its members."#
+        );
+        assert!(e.is::<GroupName>());
+        assert!(run(&mut data, &path, &["remove", "Band"]).is_err());
+        assert!(data.suspended_entries.is_empty());
+        assert_eq!(data.entries.len(), 2);
+        // contacts with a group are logged for its members,
+        // without a terminal or `--yes` as long as only the group is named
+        run(&mut data, &path, &["just-talked-to", "band"]).unwrap();
+        assert_eq!(data.entries["Anna"].interactions.len(), 1);
+        assert_eq!(data.entries["Bandit"].interactions.len(), 1);
+        let e = run(&mut data, &path, &["just-talked-to", "band", "Anna"]).unwrap_err();
+        assert!(e.is::<Unconfirmed>());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
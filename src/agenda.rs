@@ -0,0 +1,229 @@
+//! What comes up in the next weeks: due entries, birthdays and snoozes that end.
+
+use chrono::{Datelike, Duration, NaiveDate};
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::digest::next_birthday;
+use crate::table::Table;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    Due,
+    // The entry is due because its snooze ends
+    SnoozeEnds,
+    Birthday,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Event {
+    pub date: NaiveDate,
+    pub name: String,
+    pub kind: Kind,
+}
+
+impl Event {
+    fn describe(&self) -> String {
+        match self.kind {
+            Kind::Due => self.name.clone(),
+            Kind::SnoozeEnds => format!("{} (snooze ends)", self.name),
+            Kind::Birthday => format!("{}'s birthday", self.name),
+        }
+    }
+}
+
+/// The events of active entries from today to the end of the last week,
+/// and the entries that are already overdue. Weeks start on Monday.
+pub struct Agenda {
+    today: NaiveDate,
+    // Mondays of the weeks shown
+    weeks: Vec<NaiveDate>,
+    overdue: Vec<Event>,
+    upcoming: Vec<Event>,
+}
+
+impl Agenda {
+    pub fn from_table(table: &Table, today: NaiveDate, weeks: usize) -> Self {
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let weeks: Vec<NaiveDate> = (0..weeks as i64)
+            .map(|i| monday + Duration::weeks(i))
+            .collect();
+        let end = monday + Duration::weeks(weeks.len() as i64);
+        let mut events = Vec::new();
+        for e in table.entries.values() {
+            if let Some(birthday) = e.birthday {
+                events.push(Event {
+                    date: next_birthday(birthday, today),
+                    name: e.name.clone(),
+                    kind: Kind::Birthday,
+                });
+            }
+            if table.suspended_entries.contains(&e.name) {
+                continue;
+            }
+            let kind = if e.counted_from() > e.last_contact {
+                Kind::SnoozeEnds
+            } else {
+                Kind::Due
+            };
+            events.push(Event {
+                date: e.due_date(),
+                name: e.name.clone(),
+                kind,
+            });
+        }
+        events.retain(|event| event.date < end);
+        events.sort_by(|a, b| (a.date, a.kind, &a.name).cmp(&(b.date, b.kind, &b.name)));
+        let (overdue, upcoming) = events.into_iter().partition(|event| event.date < today);
+        Agenda {
+            today,
+            weeks,
+            overdue,
+            upcoming,
+        }
+    }
+
+    fn week_title(&self, i: usize, monday: NaiveDate) -> String {
+        match i {
+            0 => "This week".to_string(),
+            1 => "Next week".to_string(),
+            _ => format!("Week of {}", monday.format("%b %d")),
+        }
+    }
+
+    /// One section per week with a line for every day that has events.
+    pub fn to_text(&self) -> String {
+        let day = |date: NaiveDate, events: &[&Event]| {
+            let label = if date == self.today {
+                "Today".to_string()
+            } else {
+                date.format("%a %b %d").to_string()
+            };
+            let names: Vec<String> = events.iter().map(|event| event.describe()).collect();
+            format!("  {: <10}  {}\n", label, names.join(", "))
+        };
+        let by_day = |events: Vec<&Event>| {
+            let mut lines = String::new();
+            let mut i = 0;
+            while i < events.len() {
+                let same_day = events[i..]
+                    .iter()
+                    .take_while(|event| event.date == events[i].date)
+                    .count();
+                lines.push_str(&day(events[i].date, &events[i..i + same_day]));
+                i += same_day;
+            }
+            if lines.is_empty() {
+                lines.push_str("  -\n");
+            }
+            lines
+        };
+
+        let mut text = String::new();
+        if !self.overdue.is_empty() {
+            text.push_str(&format!("{}\n", "Overdue".bold()));
+            text.push_str(&by_day(self.overdue.iter().collect()));
+        }
+        for (i, monday) in self.weeks.iter().enumerate() {
+            let sunday = *monday + Duration::days(6);
+            let events = self
+                .upcoming
+                .iter()
+                .filter(|event| event.date >= *monday && event.date <= sunday)
+                .collect();
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(&format!("{}\n", self.week_title(i, *monday).bold()));
+            text.push_str(&by_day(events));
+        }
+        text
+    }
+
+    /// All events in order, the overdue ones first.
+    pub fn to_json(&self) -> String {
+        let events: Vec<&Event> = self.overdue.iter().chain(&self.upcoming).collect();
+        serde_json::to_string_pretty(&events).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Entry;
+    use chrono::{TimeZone, Utc};
+
+    fn agenda() -> Agenda {
+        let at = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 12, 0, 0).unwrap();
+        let mut table = Table::new();
+        // due on Thursday 2024-03-07
+        table
+            .add_entry(Entry::new("Anna".to_string(), 7, at(2024, 2, 29)))
+            .unwrap();
+        // overdue since 2024-03-01
+        table
+            .add_entry(Entry::new("Ben".to_string(), 14, at(2024, 2, 16)))
+            .unwrap();
+        // snoozed until Tuesday 2024-03-12
+        let mut carl = Entry::new("Carl".to_string(), 7, at(2024, 2, 1));
+        carl.snoozed_until = Some(at(2024, 3, 12));
+        carl.birthday = NaiveDate::from_ymd_opt(1990, 3, 7);
+        table.add_entry(carl).unwrap();
+        // suspended, and due too late anyway
+        table
+            .add_entry(Entry::new("Dan".to_string(), 90, at(2024, 3, 1)))
+            .unwrap();
+        table.suspended_entries.insert("Dan".to_string());
+        // Wednesday 2024-03-06
+        Agenda::from_table(&table, NaiveDate::from_ymd_opt(2024, 3, 6).unwrap(), 3)
+    }
+
+    #[test]
+    fn test_events() {
+        let agenda = agenda();
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        assert_eq!(agenda.weeks, vec![date(3, 4), date(3, 11), date(3, 18)]);
+        assert_eq!(
+            agenda.overdue,
+            vec![Event {
+                date: date(3, 1),
+                name: "Ben".to_string(),
+                kind: Kind::Due
+            }]
+        );
+        let upcoming: Vec<(NaiveDate, &str, Kind)> = agenda
+            .upcoming
+            .iter()
+            .map(|e| (e.date, e.name.as_str(), e.kind))
+            .collect();
+        assert_eq!(
+            upcoming,
+            vec![
+                (date(3, 7), "Anna", Kind::Due),
+                (date(3, 7), "Carl", Kind::Birthday),
+                (date(3, 12), "Carl", Kind::SnoozeEnds),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        colored::control::set_override(false);
+        assert_eq!(
+            agenda().to_text(),
+            "Overdue\n\
+            \x20 Fri Mar 01  Ben\n\
+            \n\
+            This week\n\
+            \x20 Thu Mar 07  Anna, Carl's birthday\n\
+            \n\
+            Next week\n\
+            \x20 Tue Mar 12  Carl (snooze ends)\n\
+            \n\
+            Week of Mar 18\n\
+            \x20 -\n"
+        );
+        assert!(agenda().to_json().contains(r#""kind": "snooze-ends""#));
+    }
+}
@@ -0,0 +1,719 @@
+//! The definition of the command line interface.
+
+use clap::{crate_version, App, AppSettings, Arg, ArgGroup, SubCommand};
+
+/// Subcommands that take the name of an entry as first argument.
+pub const NAME_SUBCOMMANDS: &[&str] = &[
+    "edit",
+    "just-talked-to",
+    "modify",
+    "remove",
+    "resume",
+    "stats",
+    "suspend",
+];
+
+/// The subcommands of `NAME_SUBCOMMANDS` that take several names.
+pub const MULTI_NAME_SUBCOMMANDS: &[&str] = &["just-talked-to", "remove", "resume", "suspend"];
+
+/// Fields of an entry that can be changed with `kit modify`.
+pub const FIELDS: &[&str] = &[
+    "name", "interval", "last", "aliases", "emails", "phones", "birthday", "tags", "notes",
+];
+
+/// The names of the entries a subcommand changes, unless they are chosen by `--tag`.
+fn names_arg(help: &'static str) -> Arg<'static, 'static> {
+    Arg::with_name("name")
+        .required_unless("tag")
+        .takes_value(true)
+        .multiple(true)
+        .index(1)
+        .help(help)
+}
+
+fn tag_arg() -> Arg<'static, 'static> {
+    Arg::with_name("tag")
+        .long("tag")
+        .short("t")
+        .takes_value(true)
+        .help("Change every entry with this tag.")
+}
+
+fn yes_arg() -> Arg<'static, 'static> {
+    Arg::with_name("yes").long("yes").short("y").help(
+        "Change several entries, or an entry whose name only matches approximately, \
+            without asking first. Needed for either when not running in a terminal.",
+    )
+}
+
+pub fn build_cli() -> App<'static, 'static> {
+    // called by the completion scripts
+    let complete = SubCommand::with_name("__complete")
+        .setting(AppSettings::Hidden)
+        .arg(
+            Arg::with_name("kind")
+                .required(true)
+                .takes_value(true)
+                .index(1)
+                .possible_values(&["names"]),
+        )
+        .arg(Arg::with_name("prefix").takes_value(true).index(2));
+
+    build_public_cli().subcommand(complete)
+}
+
+/// The command line interface without internal subcommands,
+/// which is what the completion scripts are generated from.
+pub fn build_public_cli() -> App<'static, 'static> {
+    App::new("kit")
+        .version(crate_version!())
+        .author("Nick Noel Machnik <nick.machnik@gmail.com>")
+        .about("Command line organizer that helps you remember to call your friends.")
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .global(true)
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("WHEN")
+                .possible_values(&["auto", "always", "never"])
+                .help(
+                    "Color the output: `auto` (the default) only in a terminal, unless \
+                    NO_COLOR or CLICOLOR_FORCE is set, `always` or `never`. \
+                    `--color` alone is `--color=always`.",
+                ),
+        )
+        .arg(
+            Arg::with_name("accessible")
+                .long("accessible")
+                .global(true)
+                .help("Name the urgency of entries in words, not only by color."),
+        )
+        .subcommands(subcommands())
+        .setting(AppSettings::ArgRequiredElseHelp)
+}
+
+/// Names of the public subcommands.
+pub fn subcommand_names() -> Vec<String> {
+    subcommands()
+        .iter()
+        .map(|s| s.get_name().to_string())
+        .collect()
+}
+
+fn subcommands() -> Vec<App<'static, 'static>> {
+    let justtalkedto = SubCommand::with_name("just-talked-to")
+        .about(
+            "Set the `last` date of one or more people, or of every member of a group, to `now`.",
+        )
+        .arg(names_arg(
+            "Names of the people or groups you just talked to.",
+        ))
+        .arg(tag_arg())
+        .arg(yes_arg());
+
+    let add = SubCommand::with_name("add")
+        .about(
+            "Add a person to your list. \
+            In a terminal, everything that is left out is asked for.",
+        )
+        .arg(
+            Arg::with_name("name")
+                .takes_value(true)
+                .index(1)
+                .help("Name of the person you want to add."),
+        )
+        .arg(
+            Arg::with_name("interval")
+                .takes_value(true)
+                .index(2)
+                .help("How regularly do you want to talk to the person (in days)?"),
+        )
+        .arg(Arg::with_name("last chat").takes_value(true).index(3).help(
+            "The date of the last chat with your friend. Either `now`, `yesterday`, \
+                a time ago like `3d ago` or `2 weeks ago`, \
+                or in a year-month-day format, e.g. `2000-5-4`.",
+        ))
+        .arg(
+            Arg::with_name("interactive")
+                .short("i")
+                .long("interactive")
+                .help(
+                    "Ask for the details step by step, with suggested intervals, \
+                    tags and contact details, and a summary before adding.",
+                ),
+        );
+
+    let suspend = SubCommand::with_name("suspend")
+        .about(
+            "Suspend an entry. \
+            Inactivates colored highlighting and shows the entry at \
+            the bottom of the table when printed.",
+        )
+        .arg(names_arg("Names of the people you want to suspend."))
+        .arg(tag_arg())
+        .arg(yes_arg());
+
+    let resume = SubCommand::with_name("resume")
+        .about("Reactivates suspended entries.")
+        .arg(names_arg("Names of the people you want to reactivate."))
+        .arg(tag_arg())
+        .arg(yes_arg());
+
+    let remove = SubCommand::with_name("remove")
+        .about("Remove people from your list.")
+        .arg(names_arg("Names of the people you want to remove."))
+        .arg(tag_arg())
+        .arg(yes_arg());
+
+    // with a tag, the field and value are given to `--tag` so that they
+    // don't end up in the positions of the name and the field
+    let modify = SubCommand::with_name("modify")
+        .about("Modify an entry, or all entries with a tag.")
+        .usage(
+            "kit modify <name> <field> <new value>\n    \
+            kit modify --tag <tag> <field> <new value>",
+        )
+        .arg(
+            Arg::with_name("name")
+                .required_unless("tag")
+                .takes_value(true)
+                .index(1)
+                .help("Name of the person whose entry you want to modify."),
+        )
+        .arg(
+            Arg::with_name("field")
+                .required_unless("tag")
+                .takes_value(true)
+                .index(2)
+                .possible_values(FIELDS)
+                .help(
+                    "The entry field you want to modify. \
+                    Aliases, emails, phones and tags are given as comma separated lists. \
+                    A birthday or notes are removed with `none`.",
+                ),
+        )
+        .arg(
+            Arg::with_name("new value")
+                .required_unless("tag")
+                .takes_value(true)
+                .index(3)
+                .help("The value you want to replace the existing value with."),
+        )
+        .arg(
+            tag_arg()
+                .number_of_values(3)
+                .value_names(&["tag", "field", "new value"])
+                .conflicts_with_all(&["name", "field", "new value"])
+                .help("Set the field of every entry with this tag."),
+        )
+        .arg(yes_arg());
+
+    let check = SubCommand::with_name("check")
+        .about(
+            "Check for active entries that need attention. \
+            Prints nothing and exits with 0 if there are none, \
+            otherwise lists them and exits with 1.",
+        )
+        .arg(
+            Arg::with_name("overdue")
+                .long("overdue")
+                .help("Only report overdue entries. This is the default."),
+        )
+        .arg(
+            Arg::with_name("due within")
+                .long("due-within")
+                .takes_value(true)
+                .conflicts_with("overdue")
+                .help(
+                    "Also report entries that are due within the given time, \
+                    in days, e.g. `3` or `3d`, or weeks, e.g. `2w`.",
+                ),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .long("quiet")
+                .short("q")
+                .help("Only print the number of entries that need attention."),
+        );
+
+    let status = SubCommand::with_name("status")
+        .about(
+            "Print a short summary of due entries. \
+            Reads a small cache instead of the whole table, \
+            so it is fast enough for shell prompts.",
+        )
+        .arg(Arg::with_name("prompt").long("prompt").short("p").help(
+            "Compact output for shell prompts. \
+                    Prints nothing while no entry is overdue or due today.",
+        ))
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .env("KIT_STATUS_FORMAT")
+                .help(
+                    "Template of the output. `{overdue}`, `{today}`, `{week}` and `{active}` \
+                    are replaced by the number of overdue entries, entries due today, \
+                    entries due within a week and all active entries.",
+                ),
+        );
+
+    let to_file = Arg::with_name("to file")
+        .long("to-file")
+        .takes_value(true)
+        .help(
+            "Append notifications to this file instead of sending them over D-Bus, \
+            e.g. for testing without a desktop session.",
+        );
+
+    let notify = SubCommand::with_name("notify")
+        .about(
+            "Send a desktop notification for every active entry that became due. \
+            Each entry is only notified about once until you get in touch.",
+        )
+        .arg(to_file.clone());
+
+    let daemon = SubCommand::with_name("daemon")
+        .about("Keep running and send notifications like `kit notify` in regular intervals.")
+        .arg(
+            Arg::with_name("every")
+                .long("every")
+                .takes_value(true)
+                .default_value("60")
+                .help("Minutes between two checks for due entries."),
+        )
+        .arg(to_file);
+
+    let digest = SubCommand::with_name("digest")
+        .about(
+            "Print a summary of overdue entries, entries due this week \
+            and upcoming birthdays, or send it by email.",
+        )
+        .after_help(
+            "Emails are sent with the settings in `config.json` next to the binary, e.g.\n\
+            {\"email\": {\"from\": \"kit@example.com\", \"to\": \"me@example.com\",\n\
+            \x20          \"smtp\": {\"host\": \"mail.example.com\", \"port\": 587,\n\
+            \x20                   \"username\": \"me\", \"password\": \"secret\"}}}\n\
+            Instead of `smtp`, a `sendmail` command can be given. \
+            The SMTP `security` is one of `starttls` (default), `tls` and `none`.",
+        )
+        .arg(
+            Arg::with_name("email")
+                .long("email")
+                .help("Send the digest by email instead of printing it."),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .takes_value(true)
+                .requires("email")
+                .help("Send the digest to this address instead of the configured one."),
+        )
+        .arg(
+            Arg::with_name("dry run")
+                .long("dry-run")
+                .requires("email")
+                .help("Print the email that would be sent instead of sending it."),
+        );
+
+    let systemd = Arg::with_name("systemd")
+        .long("systemd")
+        .help("Use systemd user timers.");
+
+    let cron = Arg::with_name("cron")
+        .long("cron")
+        .help("Use the crontab of the user.");
+
+    let install_reminders = SubCommand::with_name("install-reminders")
+        .about(
+            "Run `kit notify`, and optionally `kit digest --email`, on a schedule \
+            with systemd user timers or cron.",
+        )
+        .arg(systemd.clone())
+        .arg(cron.clone())
+        .group(
+            ArgGroup::with_name("scheduler")
+                .args(&["systemd", "cron"])
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("digest")
+                .long("digest")
+                .help("Also send the digest by email, see `kit digest --help`."),
+        )
+        .arg(
+            Arg::with_name("notify schedule")
+                .long("notify-schedule")
+                .takes_value(true)
+                .help(
+                    "When to check for due entries. A systemd calendar expression \
+                    (default: `hourly`) or five cron fields (default: `0 * * * *`).",
+                ),
+        )
+        .arg(
+            Arg::with_name("digest schedule")
+                .long("digest-schedule")
+                .takes_value(true)
+                .requires("digest")
+                .help(
+                    "When to send the digest. A systemd calendar expression \
+                    (default: `Mon 09:00`) or five cron fields (default: `0 9 * * 1`).",
+                ),
+        )
+        .arg(
+            Arg::with_name("dry run")
+                .long("dry-run")
+                .help("Print the units or the crontab instead of installing them."),
+        );
+
+    let uninstall_reminders = SubCommand::with_name("uninstall-reminders")
+        .about(
+            "Remove the reminders set up by `kit install-reminders`. \
+            Removes both kinds unless one is chosen.",
+        )
+        .arg(systemd)
+        .arg(cron);
+
+    let columns = Arg::with_name("columns")
+        .long("columns")
+        .short("c")
+        .takes_value(true)
+        .value_name("COLUMNS")
+        .help(
+            "Comma separated columns to show, out of name, remaining, due, last, \
+            interval, progress, tags, notes and urgency. Defaults to `view.columns` \
+            in the config, or name,remaining,due,last,interval,progress.",
+        );
+
+    let borders = Arg::with_name("borders")
+        .long("borders")
+        .help("Draw lines around the cells.");
+
+    let dates = Arg::with_name("dates")
+        .long("dates")
+        .takes_value(true)
+        .possible_values(&["relative", "absolute"])
+        .help(
+            "Show the remaining time and the last contact in words, like `in 3 weeks` \
+            and `yesterday`, or as numbers and dates. Defaults to `view.dates` in the \
+            config, or relative.",
+        );
+
+    let format = Arg::with_name("format")
+        .long("format")
+        .takes_value(true)
+        .possible_values(&["text", "json"])
+        .default_value("text")
+        .help("Print a table for people, or JSON for scripts.");
+
+    let view = SubCommand::with_name("view")
+        .about("View the list.")
+        .arg(columns.clone())
+        .arg(borders.clone())
+        .arg(dates.clone())
+        .arg(format.clone())
+        .arg(
+            Arg::with_name("where")
+                .long("where")
+                .short("w")
+                .takes_value(true)
+                .value_name("QUERY")
+                .help(
+                    "Only show entries matching the query, e.g. \
+                    `remaining < 0 and tag = family`. Compare name, tag, notes, \
+                    remaining, interval, last or suspended with =, !=, <, <=, >, >= \
+                    or ~ (contains), and combine them with and, or, not and brackets.",
+                ),
+        )
+        .arg(
+            Arg::with_name("sort")
+                .long("sort")
+                .short("s")
+                .takes_value(true)
+                .value_name("KEYS")
+                .help(
+                    "Sort by name, remaining, last or interval, each optionally \
+                    followed by :asc or :desc, e.g. `last:desc,name`.",
+                ),
+        )
+        .arg(
+            Arg::with_name("limit")
+                .long("limit")
+                .short("n")
+                .takes_value(true)
+                .value_name("N")
+                .help("Show at most N entries."),
+        );
+
+    let view_active = SubCommand::with_name("view-active")
+        .about("View active entries.")
+        .arg(columns.clone())
+        .arg(borders.clone())
+        .arg(dates.clone())
+        .arg(format.clone());
+
+    let view_inactive = SubCommand::with_name("view-inactive")
+        .about("View suspended entries.")
+        .arg(columns)
+        .arg(borders)
+        .arg(dates)
+        .arg(format.clone());
+
+    let agenda = SubCommand::with_name("agenda")
+        .about(
+            "Show when entries become due, birthdays and the end of snoozes \
+            in the coming weeks, day by day.",
+        )
+        .arg(
+            Arg::with_name("weeks")
+                .long("weeks")
+                .short("w")
+                .takes_value(true)
+                .default_value("4")
+                .help("Number of weeks to show, starting with this one."),
+        )
+        .arg(format.clone());
+
+    let stats = SubCommand::with_name("stats")
+        .about(
+            "Show how often you actually talk to people compared to their intervals, \
+            which tags are neglected and how many contacts there were per week. \
+            With a name, list every recorded contact with that person.",
+        )
+        .arg(
+            Arg::with_name("name")
+                .takes_value(true)
+                .index(1)
+                .help("Name of the person whose history you want to see."),
+        )
+        .arg(
+            Arg::with_name("weeks")
+                .long("weeks")
+                .short("w")
+                .takes_value(true)
+                .default_value("12")
+                .help("Number of weeks to count contacts for, up to this one."),
+        )
+        .arg(format);
+
+    let interactive = Arg::with_name("interactive")
+        .long("interactive")
+        .short("i")
+        .help("Ask for confirmation of uncertain matches instead of skipping them.");
+
+    let sync_ics = SubCommand::with_name("ics")
+        .about(
+            "Record past calendar events as contacts. \
+            Events are matched to entries by their title and attendees.",
+        )
+        .arg(
+            Arg::with_name("file")
+                .required(true)
+                .takes_value(true)
+                .index(1)
+                .help("Path to an iCalendar (.ics) file."),
+        )
+        .arg(interactive.clone());
+
+    let sync_mail = SubCommand::with_name("mail")
+        .about(
+            "Record the latest email exchange with every entry \
+            that has a matching address (see `kit modify`). \
+            Only messages that are new since the last import are read.",
+        )
+        .arg(
+            Arg::with_name("file")
+                .required(true)
+                .takes_value(true)
+                .index(1)
+                .help("Path to an mbox file or a Maildir directory."),
+        );
+
+    let sync_calls = SubCommand::with_name("calls")
+        .about(
+            "Record calls and text messages as contacts with every entry \
+            that has a matching phone number (see `kit modify`).",
+        )
+        .arg(
+            Arg::with_name("file")
+                .required(true)
+                .takes_value(true)
+                .index(1)
+                .help("Path to an XML backup of the Android app SMS Backup & Restore."),
+        )
+        .arg(
+            Arg::with_name("min duration")
+                .long("min-duration")
+                .takes_value(true)
+                .default_value("60")
+                .validator(|v| match v.parse::<u64>() {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(format!("expected a number of seconds instead of {:?}", v)),
+                })
+                .help("Shortest call in seconds that is recorded."),
+        )
+        .arg(
+            Arg::with_name("country code")
+                .long("country-code")
+                .takes_value(true)
+                .help(
+                    "Country calling code used for numbers without one, e.g. `49`. \
+                    Without it, national numbers only match if written the same way.",
+                ),
+        );
+
+    let chat_name = Arg::with_name("name")
+        .long("name")
+        .takes_value(true)
+        .help("Attribute all messages to this entry instead of matching the senders by name.");
+
+    let chat_sources = vec![
+        ("whatsapp", "Path to a WhatsApp chat text export (.txt)."),
+        (
+            "telegram",
+            "Path to a Telegram Desktop JSON export (result.json) of a chat or the whole account.",
+        ),
+        (
+            "signal",
+            "Path to a Signal text backup with lines like `[2020-12-31 21:41] Anna: Hi`.",
+        ),
+    ]
+    .into_iter()
+    .map(|(source, help)| {
+        SubCommand::with_name(source)
+            .about(
+                "Record days with chat messages as contacts. \
+                Senders are matched to entries by name.",
+            )
+            .arg(
+                Arg::with_name("file")
+                    .required(true)
+                    .takes_value(true)
+                    .index(1)
+                    .help(help),
+            )
+            .arg(chat_name.clone())
+            .arg(interactive.clone())
+    });
+
+    let sync = SubCommand::with_name("sync")
+        .about("Import contacts from other sources.")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(sync_ics)
+        .subcommand(sync_mail)
+        .subcommand(sync_calls)
+        .subcommands(chat_sources);
+
+    let group_arg = || {
+        Arg::with_name("group")
+            .required(true)
+            .takes_value(true)
+            .index(1)
+            .help("Name of the group.")
+    };
+    let members_arg = |help| {
+        Arg::with_name("members")
+            .required(true)
+            .takes_value(true)
+            .multiple(true)
+            .index(2)
+            .help(help)
+    };
+    let group = SubCommand::with_name("group")
+        .about(
+            "Manage groups of people you usually see together. \
+            `kit just-talked-to <group>` logs a contact with every member \
+            and `kit view` shows each group with its most urgent member.",
+        )
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("create")
+                .about("Create a group.")
+                .arg(group_arg())
+                .arg(members_arg("Names of the members."))
+                .arg(yes_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("delete")
+                .about("Delete a group. Its members are kept.")
+                .arg(group_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("add")
+                .about("Add members to a group.")
+                .arg(group_arg())
+                .arg(members_arg("Names of the new members."))
+                .arg(yes_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("remove")
+                .about("Remove members from a group.")
+                .arg(group_arg())
+                .arg(members_arg("Names of the members to remove."))
+                .arg(yes_arg()),
+        )
+        .subcommand(SubCommand::with_name("list").about("List the groups and their members."));
+
+    let tui = SubCommand::with_name("tui").about(
+        "Browse the list in a full-screen view, with shortcuts to log contacts, \
+        snooze, suspend and change intervals.",
+    );
+
+    let completions = SubCommand::with_name("completions")
+        .about("Print a completion script for your shell to stdout.")
+        .arg(
+            Arg::with_name("shell")
+                .required(true)
+                .takes_value(true)
+                .index(1)
+                .possible_values(&["bash", "zsh", "fish", "powershell"])
+                .help("The shell to generate the completion script for."),
+        );
+
+    let shell = SubCommand::with_name("shell").about(
+        "Run several commands in a row on a table that is only loaded once, \
+        with history and completion of commands and names.",
+    );
+
+    let edit = SubCommand::with_name("edit")
+        .about(
+            "Edit an entry, or the whole list if no name is given, as TOML in $VISUAL or $EDITOR. \
+            The changes are checked and saved together when the editor is closed, \
+            the editor opens again if there are mistakes.",
+        )
+        .arg(
+            Arg::with_name("name")
+                .takes_value(true)
+                .index(1)
+                .help("Name of the person whose entry you want to edit."),
+        );
+
+    vec![
+        add,
+        remove,
+        view,
+        tui,
+        shell,
+        check,
+        status,
+        notify,
+        daemon,
+        digest,
+        install_reminders,
+        uninstall_reminders,
+        modify,
+        edit,
+        group,
+        justtalkedto,
+        view_active,
+        view_inactive,
+        agenda,
+        stats,
+        suspend,
+        resume,
+        sync,
+        completions,
+    ]
+}
@@ -0,0 +1,265 @@
+//! Completion scripts generated from the command line definition.
+//!
+//! clap generates the subcommands and flags for every shell.
+//! Entry names are not known to clap, so each script is extended by
+//! a completion of the names for all subcommands that take one.
+//! The names are looked up with `kit __complete names` whenever
+//! a completion is requested.
+
+use clap::Shell;
+use std::io::{self, Write};
+
+use crate::cli::{build_public_cli, FIELDS, MULTI_NAME_SUBCOMMANDS, NAME_SUBCOMMANDS};
+use crate::table::Table;
+
+/// Writes the completion script for `shell` to `out`.
+pub fn generate(shell: Shell, out: &mut dyn Write) -> io::Result<()> {
+    let mut buf = Vec::new();
+    build_public_cli().gen_completions_to("kit", shell, &mut buf);
+    let script = String::from_utf8(buf).expect("Generated completion script is not valid UTF-8.");
+    let script = match shell {
+        Shell::Bash => bash(script),
+        Shell::Zsh => zsh(script),
+        Shell::Fish => fish(script),
+        Shell::PowerShell => powershell(script),
+        Shell::Elvish => script,
+    };
+    out.write_all(script.as_bytes())
+}
+
+/// The sorted names of entries and groups in the table that start with `prefix`.
+/// Names with line breaks are skipped, as the candidates are printed line by line.
+pub fn names(table: &Table, prefix: &str) -> Vec<String> {
+    let mut res = table
+        .entries
+        .keys()
+        .chain(table.groups.keys())
+        .filter(|n| n.starts_with(prefix) && !n.contains('\n'))
+        .cloned()
+        .collect::<Vec<_>>();
+    res.sort();
+    res.dedup();
+    res
+}
+
+/// Wraps the generated `_kit` function in one that completes names and fields first.
+fn bash(script: String) -> String {
+    let complete = "complete -F _kit -o bashdefault -o default kit";
+    let wrapper = format!(
+        r#"_kit_names() {{
+    # drop escapes and an opening quote of the word typed so far
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    cur="${{cur#[\'\"]}}"
+    cur="${{cur//\\/}}"
+    local name
+    COMPREPLY=()
+    while IFS= read -r name; do
+        COMPREPLY+=("$(printf '%q' "$name")")
+    done < <(kit __complete names "$cur" 2>/dev/null)
+}}
+
+_kit_with_names() {{
+    if [[ ${{COMP_CWORD}} -eq 2 && ${{COMP_WORDS[COMP_CWORD]}} != -* ]]; then
+        case "${{COMP_WORDS[1]}}" in
+            {subcommands})
+                _kit_names
+                return 0
+                ;;
+        esac
+    fi
+    if [[ ${{COMP_CWORD}} -gt 2 && ${{COMP_WORDS[COMP_CWORD]}} != -* && ${{COMP_WORDS[COMP_CWORD-1]}} != -t && ${{COMP_WORDS[COMP_CWORD-1]}} != --tag ]]; then
+        case "${{COMP_WORDS[1]}}" in
+            {multi})
+                _kit_names
+                return 0
+                ;;
+        esac
+    fi
+    if [[ ${{COMP_CWORD}} -eq 3 && ${{COMP_WORDS[1]}} == modify ]]; then
+        COMPREPLY=( $(compgen -W "{fields}" -- "${{COMP_WORDS[COMP_CWORD]}}") )
+        return 0
+    fi
+    _kit
+}}
+
+complete -F _kit_with_names -o bashdefault -o default kit"#,
+        subcommands = NAME_SUBCOMMANDS.join("|"),
+        multi = MULTI_NAME_SUBCOMMANDS.join("|"),
+        fields = FIELDS.join(" "),
+    );
+    script.replace(complete, &wrapper)
+}
+
+/// Replaces the file completion of the `name` positionals by a `_kit_names` function.
+fn zsh(script: String) -> String {
+    let mut res = String::new();
+    let mut subcommand = "";
+    for line in script.lines() {
+        if line.starts_with('(') && line.ends_with(')') {
+            subcommand = &line[1..line.len() - 1];
+        }
+        if MULTI_NAME_SUBCOMMANDS.contains(&subcommand) && line.starts_with("':name -- ") {
+            res.push_str(&line.replacen("':name -- ", "'*::name -- ", 1).replacen(
+                ":_files'",
+                ":_kit_names'",
+                1,
+            ));
+        } else if NAME_SUBCOMMANDS.contains(&subcommand)
+            && (line.starts_with("':name -- ") || line.starts_with("'::name -- "))
+            && line.contains(":_files'")
+        {
+            res.push_str(&line.replacen(":_files'", ":_kit_names'", 1));
+        } else if subcommand == "modify" && line.starts_with("':field -- ") {
+            res.push_str(&line.replacen(":_files'", &format!(":({})'", FIELDS.join(" ")), 1));
+        } else if line == "_kit \"$@\"" {
+            res.push_str(
+                "(( $+functions[_kit_names] )) ||\n\
+                 _kit_names() {\n    \
+                     local -a names\n    \
+                     names=(\"${(@f)$(kit __complete names 2>/dev/null)}\")\n    \
+                     compadd -a names\n\
+                 }\n\n",
+            );
+            res.push_str(line);
+        } else {
+            res.push_str(line);
+        }
+        res.push('\n');
+    }
+    res
+}
+
+/// Appends completions for the names and the fields of `kit modify`.
+fn fish(mut script: String) -> String {
+    script.push_str(&format!(
+        "complete -c kit -n \"__fish_seen_subcommand_from {}; and test (count (commandline -opc)) -eq 2\" -f -a \"(kit __complete names 2>/dev/null)\"\n",
+        NAME_SUBCOMMANDS.join(" "),
+    ));
+    script.push_str(&format!(
+        "complete -c kit -n \"__fish_seen_subcommand_from {}; and test (count (commandline -opc)) -gt 2; and not __fish_seen_argument -s t -l tag\" -f -a \"(kit __complete names 2>/dev/null)\"\n",
+        MULTI_NAME_SUBCOMMANDS.join(" "),
+    ));
+    script.push_str(&format!(
+        "complete -c kit -n \"__fish_seen_subcommand_from modify; and test (count (commandline -opc)) -eq 3\" -f -a \"{}\"\n",
+        FIELDS.join(" "),
+    ));
+    script
+}
+
+/// Adds the names and fields to the completion results of the matching subcommands.
+/// Names that need quoting are matched against the typed word without the quotes.
+fn powershell(script: String) -> String {
+    let names = r#"            kit __complete names 2>$null | ForEach-Object {
+                $text = if ($_ -match "[\s'`"$;(){}@,|&<>]") { "'" + ($_ -replace "'", "''") + "'" } else { $_ }
+                [CompletionResult]::new($text, $_, [CompletionResultType]::ParameterValue, $_)
+            }
+"#;
+    let field = |value: &str| {
+        format!(
+            "            [CompletionResult]::new('{0}', '{0}', [CompletionResultType]::ParameterValue, '{0}')\n",
+            value
+        )
+    };
+    let filter = "$completions.Where{ $_.CompletionText -like \"$wordToComplete*\" }";
+    let name_filter = "$completions.Where{ $_.CompletionText -like \"$wordToComplete*\" -or \
+                       $_.ListItemText -like \"$($wordToComplete.Trim(\"'\"))*\" }";
+    let mut res = String::new();
+    for line in script.lines() {
+        res.push_str(&line.replace(filter, name_filter));
+        res.push('\n');
+        let subcommand = line
+            .trim()
+            .strip_prefix("'kit;")
+            .and_then(|l| l.strip_suffix("' {"));
+        if let Some(subcommand) = subcommand {
+            if NAME_SUBCOMMANDS.contains(&subcommand) {
+                res.push_str(names);
+            }
+            if subcommand == "modify" {
+                FIELDS.iter().for_each(|f| res.push_str(&field(f)));
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::{Entry, Group};
+    use chrono::Utc;
+
+    fn script(shell: Shell) -> String {
+        let mut buf = Vec::new();
+        generate(shell, &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_names() {
+        let mut table = Table::new();
+        for name in &["Ben O'Brien", "Anna", "Ben", "Line\nBreak"] {
+            table
+                .add_entry(Entry::new(name.to_string(), 7, Utc::now()))
+                .unwrap();
+        }
+        table
+            .add_group(Group {
+                name: "Band".to_string(),
+                members: vec!["Ben".to_string()],
+            })
+            .unwrap();
+        assert_eq!(
+            names(&table, ""),
+            vec!["Anna", "Band", "Ben", "Ben O'Brien"]
+        );
+        assert_eq!(names(&table, "Ben "), vec!["Ben O'Brien"]);
+        assert!(names(&table, "b").is_empty());
+    }
+
+    #[test]
+    fn test_bash_completes_names() {
+        let script = script(Shell::Bash);
+        assert!(script.contains(r#"done < <(kit __complete names "$cur" 2>/dev/null)"#));
+        assert!(script.contains("edit|just-talked-to|modify|remove|resume|stats|suspend)"));
+        assert!(script.contains("            just-talked-to|remove|resume|suspend)"));
+        assert!(script.contains("complete -F _kit_with_names"));
+        assert!(!script.contains("complete -F _kit -o"));
+    }
+
+    #[test]
+    fn test_zsh_completes_names() {
+        let script = script(Shell::Zsh);
+        assert!(script.contains("'*::name -- Names of the people you want to remove.:_kit_names'"));
+        assert!(script
+            .contains("'::name -- Name of the person whose entry you want to edit.:_kit_names'"));
+        assert!(script.contains(r#"names=("${(@f)$(kit __complete names 2>/dev/null)}")"#));
+        assert!(script.contains(":(name interval last aliases emails phones birthday tags notes)'"));
+        assert!(script.trim_end().ends_with("_kit \"$@\""));
+    }
+
+    #[test]
+    fn test_fish_completes_names() {
+        let script = script(Shell::Fish);
+        assert!(script.contains(r#"-f -a "(kit __complete names 2>/dev/null)""#));
+        assert!(script.contains(r#"-eq 3" -f -a "name interval last"#));
+    }
+
+    #[test]
+    fn test_powershell_completes_names() {
+        let script = script(Shell::PowerShell);
+        let remove = script.split("'kit;remove' {").nth(1).unwrap();
+        assert!(remove.starts_with("\n            kit __complete names 2>$null"));
+        let view = script.split("'kit;view' {").nth(1).unwrap();
+        assert!(!view.split('}').next().unwrap().contains("__complete"));
+        assert!(script.contains("$_.ListItemText -like"));
+    }
+
+    #[test]
+    fn test_complete_is_hidden() {
+        for shell in &[Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+            assert!(!script(*shell).contains("-a \"__complete\""));
+            assert!(!script(*shell).contains("'__complete'"));
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! User settings, read from `config.json` next to the binary.
+
+use serde::{Deserialize, Serialize};
+use std::error;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::hooks::Hook;
+use crate::render::Dates;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub email: Option<EmailConfig>,
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+    #[serde(default)]
+    pub view: ViewConfig,
+}
+
+/// Defaults for the layout of `kit view`, overridden by its options.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ViewConfig {
+    // Names of the columns to show, e.g. `["name", "due", "tags"]`
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub borders: bool,
+    // Adds a column that names the urgency of every entry
+    #[serde(default)]
+    pub accessible: bool,
+    // `relative` (the default) or `absolute`
+    #[serde(default)]
+    pub dates: Dates,
+}
+
+/// Where digests are sent and how. An SMTP server is preferred over `sendmail`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailConfig {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub smtp: Option<SmtpConfig>,
+    // Path of a sendmail compatible command, e.g. `/usr/sbin/sendmail`
+    #[serde(default)]
+    pub sendmail: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub security: Security,
+}
+
+/// How the connection to the SMTP server is secured. Other values
+/// are rejected when the config is read.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    #[default]
+    Starttls,
+    Tls,
+    None,
+}
+
+impl Config {
+    /// Reads the config file. A missing file is the same as an empty one.
+    pub fn from_json(path: &Path) -> Result<Self, Box<dyn error::Error>> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let json_file_str = read_to_string(path)?;
+        let data = serde_json::from_str(&json_file_str)?;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_defaults() {
+        let config: Config = serde_json::from_str(
+            r#"{"email": {"from": "kit@example.com", "to": "me@example.com",
+                "smtp": {"host": "mail.example.com"}}}"#,
+        )
+        .unwrap();
+        let email = config.email.unwrap();
+        assert_eq!(Security::Starttls, email.smtp.unwrap().security);
+        assert!(email.sendmail.is_none());
+    }
+
+    #[test]
+    fn test_unknown_security() {
+        let e = serde_json::from_str::<Config>(
+            r#"{"email": {"from": "kit@example.com", "to": "me@example.com",
+                "smtp": {"host": "mail.example.com", "security": "ssl"}}}"#,
+        )
+        .unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("unknown variant `ssl`, expected one of `starttls`, `tls`, `none`"));
+    }
+}
@@ -0,0 +1,312 @@
+//! A summary of overdue and upcoming contacts that can be sent by email.
+
+use chrono::{Datelike, NaiveDate};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SendmailTransport, SmtpTransport, Transport};
+use std::error;
+use std::fmt;
+
+use crate::config::{EmailConfig, Security};
+use crate::table::{Entry, Table};
+
+#[derive(Debug, Clone)]
+pub struct NoTransport;
+
+impl fmt::Display for NoTransport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Neither an SMTP server nor a sendmail command is configured"
+        )
+    }
+}
+
+impl error::Error for NoTransport {
+    fn description(&self) -> &str {
+        "No email transport configured"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+/// The next birthday on or after `today`. Birthdays on February 29
+/// are celebrated on February 28 in other years.
+pub fn next_birthday(birthday: NaiveDate, today: NaiveDate) -> NaiveDate {
+    let in_year = |year| {
+        NaiveDate::from_ymd_opt(year, birthday.month(), birthday.day())
+            .or_else(|| NaiveDate::from_ymd_opt(year, 2, 28))
+            .unwrap()
+    };
+    let this_year = in_year(today.year());
+    if this_year >= today {
+        this_year
+    } else {
+        in_year(today.year() + 1)
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Overdue entries, entries due within a week and birthdays within a week,
+/// all of them active. Expects up to date remaining times.
+pub struct Digest<'a> {
+    overdue: Vec<&'a Entry>,
+    due: Vec<&'a Entry>,
+    birthdays: Vec<(&'a Entry, NaiveDate)>,
+}
+
+impl<'a> Digest<'a> {
+    pub fn from_table(table: &'a Table, today: NaiveDate) -> Self {
+        let (overdue, due) = table
+            .due_entries(6)
+            .into_iter()
+            .partition(|e| e.remaining_time() < 0);
+        let mut birthdays = table
+            .entries
+            .values()
+            .filter(|e| !table.suspended_entries.contains(&e.name))
+            .filter_map(|e| e.birthday.map(|b| (e, next_birthday(b, today))))
+            .filter(|(_, b)| b.signed_duration_since(today).num_days() < 7)
+            .collect::<Vec<_>>();
+        birthdays.sort_by_key(|(e, b)| (*b, &e.name));
+        Digest {
+            overdue,
+            due,
+            birthdays,
+        }
+    }
+
+    pub fn subject(&self) -> String {
+        format!(
+            "kit: {} overdue, {} due this week, {} birthday(s)",
+            self.overdue.len(),
+            self.due.len(),
+            self.birthdays.len()
+        )
+    }
+
+    fn sections(&self) -> Vec<(&'static str, Vec<String>)> {
+        let describe = |e: &&Entry| format!("{}: {}", e.name, e.remaining_description());
+        vec![
+            ("Overdue", self.overdue.iter().map(describe).collect()),
+            ("Due this week", self.due.iter().map(describe).collect()),
+            (
+                "Upcoming birthdays",
+                self.birthdays
+                    .iter()
+                    .map(|(e, b)| format!("{}: {}", e.name, b.format("%A, %Y-%m-%d")))
+                    .collect(),
+            ),
+        ]
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (title, lines) in self.sections() {
+            text.push_str(&format!("{}\n", title));
+            if lines.is_empty() {
+                text.push_str("  -\n");
+            }
+            for line in lines {
+                text.push_str(&format!("  {}\n", line));
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::from("<html><body>\n");
+        for (title, lines) in self.sections() {
+            html.push_str(&format!("<h2>{}</h2>\n", title));
+            if lines.is_empty() {
+                html.push_str("<p>-</p>\n");
+                continue;
+            }
+            html.push_str("<ul>\n");
+            for line in lines {
+                html.push_str(&format!("<li>{}</li>\n", escape_html(&line)));
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</body></html>\n");
+        html
+    }
+
+    /// Builds an email with a plain text and an HTML version of the digest.
+    pub fn to_message(&self, from: &str, to: &str) -> Result<Message, Box<dyn error::Error>> {
+        let message = Message::builder()
+            .from(from.parse::<Mailbox>()?)
+            .to(to.parse::<Mailbox>()?)
+            .subject(self.subject())
+            .multipart(MultiPart::alternative_plain_html(
+                self.to_text(),
+                self.to_html(),
+            ))?;
+        Ok(message)
+    }
+}
+
+/// Sends `message` with the SMTP server or the sendmail command from `config`.
+pub fn send(message: &Message, config: &EmailConfig) -> Result<(), Box<dyn error::Error>> {
+    if let Some(smtp) = &config.smtp {
+        let mut builder = match smtp.security {
+            Security::None => SmtpTransport::builder_dangerous(&smtp.host),
+            Security::Tls => SmtpTransport::relay(&smtp.host)?,
+            Security::Starttls => SmtpTransport::starttls_relay(&smtp.host)?,
+        };
+        if let Some(port) = smtp.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        builder.build().send(message)?;
+    } else if let Some(command) = &config.sendmail {
+        SendmailTransport::new_with_command(command).send(message)?;
+    } else {
+        return Err(Box::new(NoTransport));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SmtpConfig;
+    use chrono::{Duration, TimeZone, Utc};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    fn table() -> Table {
+        let mut table = Table::new();
+        let now = Utc::now();
+        let entries = vec![("Anna <3", 5, 10), ("Ben", 30, 25), ("Carla", 30, 1)];
+        for (name, interval, days_ago) in entries {
+            let last = now - Duration::days(days_ago);
+            table
+                .add_entry(Entry::new(name.to_string(), interval, last))
+                .unwrap();
+        }
+        let today = now.date_naive();
+        table.entries.get_mut("Carla").unwrap().birthday =
+            Some(NaiveDate::from_ymd_opt(1990, today.month(), today.day()).unwrap_or(today));
+        table
+    }
+
+    #[test]
+    fn test_next_birthday() {
+        let today = NaiveDate::from_ymd_opt(2021, 3, 1).unwrap();
+        let leap = NaiveDate::from_ymd_opt(2000, 2, 29).unwrap();
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2022, 2, 28).unwrap(),
+            next_birthday(leap, today)
+        );
+        let spring = NaiveDate::from_ymd_opt(1990, 3, 1).unwrap();
+        assert_eq!(today, next_birthday(spring, today));
+    }
+
+    #[test]
+    fn test_digest_text_and_html() {
+        let table = table();
+        let digest = Digest::from_table(&table, Utc::now().date_naive());
+        assert_eq!(
+            "kit: 1 overdue, 1 due this week, 1 birthday(s)",
+            digest.subject()
+        );
+        let text = digest.to_text();
+        assert!(text.contains("Overdue\n  Anna <3: overdue by 5 day(s)\n"));
+        assert!(text.contains("Due this week\n  Ben: due in 5 day(s)\n"));
+        assert!(digest
+            .to_html()
+            .contains("<li>Anna &lt;3: overdue by 5 day(s)</li>"));
+        // suspended entries are left out like in the other sections
+        let mut suspended = table.clone();
+        suspended.suspend_entry("Carla".to_string()).unwrap();
+        let digest = Digest::from_table(&suspended, Utc::now().date_naive());
+        assert!(digest.birthdays.is_empty());
+    }
+
+    #[test]
+    fn test_send_over_smtp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // a minimal stand-in SMTP server that accepts a single message
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            writer.write_all(b"220 localhost\r\n").unwrap();
+            let mut data = String::new();
+            let mut in_data = false;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 {
+                if in_data {
+                    if line == ".\r\n" {
+                        in_data = false;
+                        writer.write_all(b"250 OK\r\n").unwrap();
+                    } else {
+                        data.push_str(&line);
+                    }
+                } else if line.starts_with("DATA") {
+                    in_data = true;
+                    writer.write_all(b"354 Go ahead\r\n").unwrap();
+                } else if line.starts_with("QUIT") {
+                    writer.write_all(b"221 Bye\r\n").unwrap();
+                    break;
+                } else {
+                    writer.write_all(b"250 OK\r\n").unwrap();
+                }
+                line.clear();
+            }
+            data
+        });
+        let table = table();
+        let digest = Digest::from_table(&table, Utc.timestamp_opt(0, 0).unwrap().date_naive());
+        let message = digest
+            .to_message("kit@example.com", "me@example.com")
+            .unwrap();
+        let config = EmailConfig {
+            from: "kit@example.com".to_string(),
+            to: "me@example.com".to_string(),
+            smtp: Some(SmtpConfig {
+                host: "127.0.0.1".to_string(),
+                port: Some(port),
+                username: None,
+                password: None,
+                security: Security::None,
+            }),
+            sendmail: None,
+        };
+        send(&message, &config).unwrap();
+        let data = server.join().unwrap();
+        assert!(data.contains("Subject: kit: 1 overdue"));
+        assert!(data.contains("Content-Type: text/plain"));
+        assert!(data.contains("Content-Type: text/html"));
+    }
+
+    #[test]
+    fn test_send_without_transport() {
+        let table = table();
+        let digest = Digest::from_table(&table, Utc::now().date_naive());
+        let message = digest
+            .to_message("kit@example.com", "me@example.com")
+            .unwrap();
+        let config = EmailConfig {
+            from: "kit@example.com".to_string(),
+            to: "me@example.com".to_string(),
+            smtp: None,
+            sendmail: None,
+        };
+        assert!(send(&message, &config).is_err());
+    }
+}
@@ -0,0 +1,470 @@
+//! Editing entries as TOML in the user's editor.
+//!
+//! The entries are written to a temporary file, one section per entry keyed
+//! by its current name. The edited file is checked completely before anything
+//! is changed, so either all changes are saved or none.
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs::{read_to_string, write};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use toml::Spanned;
+
+use crate::helpers::get_hooks;
+use crate::hooks;
+use crate::parse::parse_date;
+use crate::table::{Entry, Table};
+
+const HEADER: &str = "\
+# Edit the entries below, then save and close the editor to apply the changes.
+# Change `name` to rename an entry, delete a section to remove its entry and
+# add a section to add one. Dates are YEAR-MONTH-DAY. An empty file cancels.
+";
+
+/// Prefix of the lines that list the problems of the last attempt.
+const PROBLEM_PREFIX: &str = "# ! ";
+
+/// An entry as it is shown in the editor.
+#[derive(Debug, PartialEq, Serialize)]
+struct EditEntry {
+    name: String,
+    interval: usize,
+    last: String,
+    suspended: bool,
+    aliases: Vec<String>,
+    emails: Vec<String>,
+    phones: Vec<String>,
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    birthday: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+}
+
+impl EditEntry {
+    fn from_entry(entry: &Entry, suspended: bool) -> Self {
+        EditEntry {
+            name: entry.name.clone(),
+            interval: entry.interval,
+            last: entry.last_contact.format("%Y-%m-%d").to_string(),
+            suspended,
+            aliases: entry.aliases.clone(),
+            emails: entry.emails.clone(),
+            phones: entry.phones.clone(),
+            tags: entry.tags.clone(),
+            birthday: entry.birthday.map(|b| b.format("%Y-%m-%d").to_string()),
+            notes: entry.notes.clone(),
+        }
+    }
+}
+
+/// An entry as it is read back, with positions for error messages.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EditedEntry {
+    name: Option<Spanned<String>>,
+    interval: usize,
+    last: Spanned<String>,
+    #[serde(default)]
+    suspended: bool,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    emails: Vec<String>,
+    #[serde(default)]
+    phones: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    birthday: Option<Spanned<String>>,
+    notes: Option<String>,
+}
+
+/// Problems found in an edited file, each with the line it is on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidEdit {
+    pub problems: Vec<(usize, String)>,
+}
+
+impl fmt::Display for InvalidEdit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines: Vec<String> = self
+            .problems
+            .iter()
+            .map(|(line, problem)| format!("line {}: {}", line, problem))
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for InvalidEdit {
+    fn description(&self) -> &str {
+        "Invalid edit"
+    }
+
+    fn cause(&self) -> Option<&dyn std::error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+/// The changes made by an edit, by name.
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+/// The file shown in the editor for the given entries.
+pub fn render(table: &Table, names: &[String]) -> String {
+    let entries: BTreeMap<&str, EditEntry> = names
+        .iter()
+        .map(|n| {
+            (
+                n.as_str(),
+                EditEntry::from_entry(&table.entries[n], table.suspended_entries.contains(n)),
+            )
+        })
+        .collect();
+    format!(
+        "{}\n{}",
+        HEADER,
+        toml::to_string(&entries).expect("Entries can always be written as TOML.")
+    )
+}
+
+/// Whether the file has nothing but comments left.
+fn is_empty(text: &str) -> bool {
+    text.lines()
+        .map(str::trim)
+        .all(|l| l.is_empty() || l.starts_with('#'))
+}
+
+/// Applies the edited version of the entries `names` to a copy of the table.
+/// Entries that aren't in `names` are kept as they are.
+pub fn apply(table: &Table, names: &[String], text: &str) -> Result<(Table, Changes), InvalidEdit> {
+    let edited: BTreeMap<Spanned<String>, EditedEntry> =
+        toml::from_str(text).map_err(|e| InvalidEdit {
+            problems: vec![(
+                line_of(text, e.span().map_or(0, |s| s.start)),
+                e.message().to_string(),
+            )],
+        })?;
+    let mut new_table = table.clone();
+    let mut previous = BTreeMap::new();
+    for name in names {
+        let suspended = new_table.suspended_entries.remove(name);
+        if let Some(entry) = new_table.entries.remove(name) {
+            previous.insert(name.clone(), (entry, suspended));
+        }
+    }
+    let mut problems = Vec::new();
+    let mut changes = Changes::default();
+    let mut renamed = HashMap::new();
+    for (key, e) in &edited {
+        let (new_name, name_line) = match &e.name {
+            Some(name) => (
+                name.get_ref().trim().to_string(),
+                line_of(text, name.span().start),
+            ),
+            None => (key.get_ref().clone(), line_of(text, key.span().start)),
+        };
+        if new_name.is_empty() {
+            problems.push((name_line, "The name is empty.".to_string()));
+            continue;
+        }
+        if new_table.entries.contains_key(&new_name) || new_table.groups.contains_key(&new_name) {
+            problems.push((name_line, format!("Name {:?} is already used.", new_name)));
+            continue;
+        }
+        let last_contact = match parse_date(e.last.get_ref()) {
+            Ok(date) => date,
+            Err(err) => {
+                problems.push((line_of(text, e.last.span().start), err.to_string()));
+                continue;
+            }
+        };
+        let birthday = match &e.birthday {
+            None => None,
+            Some(raw) => match parse_date(raw.get_ref()) {
+                Ok(date) => Some(date.date_naive()),
+                Err(err) => {
+                    problems.push((line_of(text, raw.span().start), err.to_string()));
+                    continue;
+                }
+            },
+        };
+        let old = previous.get(key.get_ref());
+        let mut entry = match old {
+            Some((entry, _)) => entry.clone(),
+            None => Entry::new(new_name.clone(), e.interval, last_contact),
+        };
+        entry.name = new_name.clone();
+        entry.interval = e.interval;
+        // the file only has the day, keep the time of day if that didn't change
+        if entry.last_contact.date_naive() != last_contact.date_naive() {
+            entry.last_contact = last_contact;
+        }
+        entry.aliases = e.aliases.clone();
+        entry.emails = e.emails.clone();
+        entry.phones = e.phones.clone();
+        entry.tags = e.tags.clone();
+        entry.birthday = birthday;
+        entry.notes = e.notes.clone();
+        entry.update_remaining_time();
+        if e.suspended {
+            new_table.suspended_entries.insert(new_name.clone());
+        }
+        match old {
+            None => changes.added.push(new_name.clone()),
+            Some((old_entry, was_suspended)) => {
+                renamed.insert(key.get_ref().clone(), new_name.clone());
+                if EditEntry::from_entry(old_entry, *was_suspended)
+                    != EditEntry::from_entry(&entry, e.suspended)
+                {
+                    changes.modified.push(new_name.clone());
+                }
+            }
+        }
+        new_table.entries.insert(new_name, entry);
+    }
+    if !problems.is_empty() {
+        problems.sort();
+        return Err(InvalidEdit { problems });
+    }
+    changes.removed = previous
+        .into_iter()
+        .filter(|(name, _)| !edited.keys().any(|k| k.get_ref() == name))
+        .map(|(name, _)| name)
+        .collect();
+    for group in new_table.groups.values_mut() {
+        group.members.retain(|m| !changes.removed.contains(m));
+        for member in group.members.iter_mut() {
+            if let Some(new_name) = renamed.get(member) {
+                *member = new_name.clone();
+            }
+        }
+    }
+    Ok((new_table, changes))
+}
+
+/// Replaces the problems listed at the top of `text` by the new ones,
+/// with line numbers that match the new file.
+fn with_problems(text: &str, e: &InvalidEdit) -> String {
+    let old_count = text
+        .lines()
+        .take_while(|l| l.starts_with(PROBLEM_PREFIX))
+        .count();
+    let new_count = e.problems.len() + 1;
+    let mut out = format!("{}The changes could not be applied:\n", PROBLEM_PREFIX);
+    for (line, problem) in &e.problems {
+        out.push_str(&format!(
+            "{}line {}: {}\n",
+            PROBLEM_PREFIX,
+            line + new_count - old_count,
+            problem
+        ));
+    }
+    for l in text.lines().skip(old_count) {
+        out.push_str(l);
+        out.push('\n');
+    }
+    out
+}
+
+fn open_editor(path: &Path) -> Result<(), Box<dyn Error>> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let words = shell_words::split(&editor)?;
+    let (program, args) = words.split_first().ok_or("The editor command is empty.")?;
+    let status = Command::new(program).args(args).arg(path).status()?;
+    if !status.success() {
+        return Err(format!("{} exited with {}.", editor, status).into());
+    }
+    Ok(())
+}
+
+/// Opens the entries `names` in the editor until the result is valid,
+/// then saves the table and runs the hooks for every change.
+pub fn run(data: &mut Table, table_path: &Path, names: &[String]) -> Result<(), Box<dyn Error>> {
+    let original = render(data, names);
+    // created with a random name that must not exist yet and only readable by the user,
+    // the file is removed when `path` is dropped, on errors as well
+    let mut file = tempfile::Builder::new()
+        .prefix("kit-edit-")
+        .suffix(".toml")
+        .tempfile()?;
+    file.write_all(original.as_bytes())?;
+    let path = file.into_temp_path();
+    let result = loop {
+        if let Err(e) = open_editor(&path) {
+            break Err(e);
+        }
+        let text = read_to_string(&path)?;
+        if is_empty(&text) {
+            info!("Empty file, nothing changed.");
+            break Ok(None);
+        }
+        match apply(data, names, &text) {
+            Ok(applied) => break Ok(Some(applied)),
+            Err(e) => {
+                error!("{}", e);
+                write(&path, with_problems(&text, &e))?;
+            }
+        }
+    };
+    let _ = path.close();
+    let (new_table, changes) = match result? {
+        Some(applied) => applied,
+        None => return Ok(()),
+    };
+    if changes.is_empty() {
+        info!("Nothing changed.");
+        return Ok(());
+    }
+    let removed: Vec<Entry> = changes
+        .removed
+        .iter()
+        .map(|n| data.entries[n].clone())
+        .collect();
+    *data = new_table;
+    data.to_json(table_path);
+    let hooks = get_hooks();
+    for name in &changes.added {
+        info!("Added {:?}.", name);
+        hooks::run(&hooks, "entry.added", &data.entries[name], None);
+    }
+    for entry in &removed {
+        info!("Removed {:?}.", entry.name);
+        hooks::run(&hooks, "entry.removed", entry, None);
+    }
+    for name in &changes.modified {
+        info!("Modified {:?}.", name);
+        hooks::run(&hooks, "entry.modified", &data.entries[name], None);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_date;
+    use crate::table::Group;
+
+    fn table() -> Table {
+        let mut table = Table::new();
+        for name in &["Anna", "Ben O'Brien"] {
+            table
+                .add_entry(Entry::new(
+                    name.to_string(),
+                    7,
+                    parse_date("2020-1-1").unwrap(),
+                ))
+                .unwrap();
+        }
+        table.suspended_entries.insert("Anna".to_string());
+        table
+            .add_group(Group {
+                name: "Band".to_string(),
+                members: vec!["Anna".to_string(), "Ben O'Brien".to_string()],
+            })
+            .unwrap();
+        table
+    }
+
+    fn all(table: &Table) -> Vec<String> {
+        let mut names: Vec<String> = table.entries.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let table = table();
+        let text = render(&table, &all(&table));
+        assert!(text.contains("[\"Ben O'Brien\"]"));
+        let (new_table, changes) = apply(&table, &all(&table), &text).unwrap();
+        assert_eq!(changes, Changes::default());
+        assert_eq!(new_table.entries.len(), 2);
+        assert!(new_table.suspended_entries.contains("Anna"));
+    }
+
+    #[test]
+    fn test_changes() {
+        let table = table();
+        let names = vec!["Anna".to_string()];
+        let text = render(&table, &names)
+            .replace("name = \"Anna\"", "name = \"Anne\"")
+            .replace("suspended = true", "suspended = false")
+            + "\n[Carl]\ninterval = 3\nlast = \"2021-2-3\"\ntags = [\"work\"]\n";
+        let (new_table, changes) = apply(&table, &names, &text).unwrap();
+        assert_eq!(changes.added, vec!["Carl"]);
+        assert_eq!(changes.modified, vec!["Anne"]);
+        assert!(changes.removed.is_empty());
+        assert!(new_table.suspended_entries.is_empty());
+        assert!(new_table.entries["Carl"].has_tag("work"));
+        assert!(new_table.entries.contains_key("Ben O'Brien"));
+        assert!(!new_table.entries.contains_key("Anna"));
+
+        assert_eq!(
+            new_table.groups["Band"].members,
+            vec!["Anne", "Ben O'Brien"]
+        );
+
+        let (new_table, changes) = apply(&table, &names, HEADER).unwrap();
+        assert_eq!(changes.removed, vec!["Anna"]);
+        assert_eq!(new_table.entries.len(), 1);
+        assert_eq!(new_table.groups["Band"].members, vec!["Ben O'Brien"]);
+    }
+
+    #[test]
+    fn test_problems() {
+        let table = table();
+        let names = vec!["Anna".to_string()];
+        let text = render(&table, &names)
+            .replace("2020-01-01", "2020-13-01")
+            .replace("name = \"Anna\"", "name = \"Ben O'Brien\"");
+        let e = apply(&table, &names, &text).unwrap_err();
+        assert_eq!(
+            e.problems,
+            vec![(6, r#"Name "Ben O'Brien" is already used."#.to_string())]
+        );
+        let text = text.replace("Ben O'Brien", "Anna");
+        let e = apply(&table, &names, &text).unwrap_err();
+        assert_eq!(e.problems.len(), 1);
+        assert_eq!(e.problems[0].0, 8);
+        assert!(apply(&table, &names, &format!("{}color = 1\n", text))
+            .unwrap_err()
+            .to_string()
+            .contains("unknown field"));
+
+        let shown = with_problems(&text, &e);
+        assert!(shown.starts_with(PROBLEM_PREFIX));
+        assert_eq!(shown.lines().nth(9).unwrap(), "last = \"2020-13-01\"");
+        assert!(shown.contains("line 10: Invalid date"));
+        let again = with_problems(&shown, &apply(&table, &names, &shown).unwrap_err());
+        assert_eq!(again, shown);
+    }
+
+    #[test]
+    fn test_empty() {
+        assert!(is_empty("# comment\n\n   \n"));
+        assert!(!is_empty("[Anna]\n"));
+    }
+}
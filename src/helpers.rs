@@ -1,66 +1,202 @@
 //! Small helper functions that perform often used operations.
 
-use chrono::prelude::DateTime;
-use chrono::prelude::Utc;
+use chrono::{NaiveDate, Utc};
 use log::{error, info};
-use regex::Regex;
-use std::fs::{read_to_string, File};
+use std::error;
 use std::io;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
 
-use crate::parse::parse_date;
+use crate::config::{Config, ViewConfig};
+use crate::hooks::Hook;
+use crate::names::{lookup, AmbiguousName, Lookup, UncertainName, UnknownName};
 use crate::table::Table;
-use crate::COMPLETION_LOC;
+use crate::CONFIG_LOC;
+use crate::NOTIFIED_LOC;
+use crate::SHELL_HISTORY_LOC;
+use crate::STATUS_LOC;
+use crate::SYNC_STATE_LOC;
 use crate::TABLE_LOC;
 
+/// The date of today. Contacts are stored in UTC and due dates are UTC dates,
+/// so today is the UTC date as well. With the local date an entry could be due
+/// today in one command and tomorrow in another around midnight.
+pub fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
 pub fn get_table_path() -> PathBuf {
     let mut outpath = std::env::current_exe().unwrap();
     outpath.set_file_name(TABLE_LOC);
     outpath
 }
 
-pub fn get_interval(raw: &str) -> usize {
-    match raw.parse() {
-        Ok(num) => num,
+pub fn get_config_path() -> PathBuf {
+    let mut outpath = std::env::current_exe().unwrap();
+    outpath.set_file_name(CONFIG_LOC);
+    outpath
+}
+
+/// Reads the hooks from the config. A broken config is reported,
+/// but does not stop the command that runs the hooks.
+pub fn get_hooks() -> Vec<Hook> {
+    match Config::from_json(&get_config_path()) {
+        Ok(config) => config.hooks,
         Err(e) => {
-            error!(
-                "Parsing the interval field failed: {:?}. Please enter an integer.",
-                e
-            );
-            std::process::exit(exitcode::USAGE);
+            error!("Reading the config failed, no hooks are run: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Reads the view settings from the config. A broken config is reported,
+/// and the default layout is used instead.
+pub fn get_view_config() -> ViewConfig {
+    match Config::from_json(&get_config_path()) {
+        Ok(config) => config.view,
+        Err(e) => {
+            error!("Reading the config failed, using the default layout: {}", e);
+            ViewConfig::default()
+        }
+    }
+}
+
+pub fn get_notified_path() -> PathBuf {
+    let mut outpath = std::env::current_exe().unwrap();
+    outpath.set_file_name(NOTIFIED_LOC);
+    outpath
+}
+
+pub fn get_status_path() -> PathBuf {
+    let mut outpath = std::env::current_exe().unwrap();
+    outpath.set_file_name(STATUS_LOC);
+    outpath
+}
+
+pub fn get_shell_history_path() -> PathBuf {
+    let mut outpath = std::env::current_exe().unwrap();
+    outpath.set_file_name(SHELL_HISTORY_LOC);
+    outpath
+}
+
+pub fn get_sync_state_path() -> PathBuf {
+    let mut outpath = std::env::current_exe().unwrap();
+    outpath.set_file_name(SYNC_STATE_LOC);
+    outpath
+}
+
+/// Splits a comma separated list, dropping empty elements.
+pub fn get_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(|e| e.trim().to_string())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+
+/// Resolves a name typed on the command line to the name of an entry.
+/// Ambiguous names are resolved by asking if stdin is a terminal.
+pub fn resolve_name(table: &Table, raw: &str) -> Result<String, Box<dyn error::Error>> {
+    resolve(table, raw, false, false)
+}
+
+/// Like `resolve_name`, but for changes to entries. Names that only match
+/// as a prefix or with typos have to be confirmed in a terminal, or accepted
+/// up front with `yes`, and are rejected otherwise.
+pub fn resolve_name_strictly(
+    table: &Table,
+    raw: &str,
+    yes: bool,
+) -> Result<String, Box<dyn error::Error>> {
+    resolve(table, raw, true, yes)
+}
+
+fn resolve(
+    table: &Table,
+    raw: &str,
+    strict: bool,
+    yes: bool,
+) -> Result<String, Box<dyn error::Error>> {
+    let name = match lookup(table, raw) {
+        Lookup::Found(name, how) if strict && !how.is_certain() && !yes => {
+            if !io::stdin().is_terminal() {
+                return Err(Box::new(UncertainName {
+                    name: raw.to_string(),
+                    candidate: name,
+                }));
+            }
+            if !confirm(&format!("Did you mean {:?}?", name)) {
+                return Err(Box::new(UnknownName {
+                    name: raw.to_string(),
+                    suggestions: vec![name],
+                }));
+            }
+            name
+        }
+        Lookup::Found(name, _) => name,
+        Lookup::Ambiguous(names) if io::stdin().is_terminal() => {
+            match choose(&format!("{:?} matches several entries:", raw), &names) {
+                Some(i) => names[i].clone(),
+                None => {
+                    return Err(Box::new(AmbiguousName {
+                        name: raw.to_string(),
+                        candidates: names,
+                    }))
+                }
+            }
+        }
+        Lookup::Ambiguous(names) => {
+            return Err(Box::new(AmbiguousName {
+                name: raw.to_string(),
+                candidates: names,
+            }))
+        }
+        Lookup::Missing(names) => {
+            return Err(Box::new(UnknownName {
+                name: raw.to_string(),
+                suggestions: names,
+            }))
         }
+    };
+    if name != raw {
+        info!("Using {:?} for {:?}.", name, raw);
     }
+    Ok(name)
 }
 
-pub fn get_date(raw: &str) -> DateTime<Utc> {
-    match parse_date(raw) {
-        Ok(date) => date,
+/// Like `resolve_name`, but exits if the name can't be resolved.
+pub fn get_name(table: &Table, raw: &str) -> String {
+    match resolve_name(table, raw) {
+        Ok(name) => name,
         Err(e) => {
-            error!(
-                "Parsing the date string failed: {:?}. Required format: YEAR-MONTH-DAY",
-                e
-            );
+            error!("{}", e);
             std::process::exit(exitcode::USAGE);
         }
     }
 }
 
-/// This updates the names in the kit-complete.sh script.
-pub fn update_autocomplete_names(table: &Table) -> Result<(), io::Error> {
-    let mut compl_path = std::env::current_exe().unwrap();
-    compl_path.set_file_name(COMPLETION_LOC);
-    let data = read_to_string(&compl_path)?;
-    let re = Regex::new(r#"-W "(?s)(.*)" --"#).unwrap();
-    let names = table
-        .entries
-        .keys()
-        .map(|s| s.to_string())
-        .collect::<Vec<_>>()
-        .join(" ");
-    let new_data = re.replace(&data, format!(r#"-W "{}" --"#, &names).as_str());
-    let mut dst = File::create(&compl_path)?;
-    dst.write_all(new_data.as_bytes())?;
-    info!("Updated names for autocompletion.");
-    Ok(())
+/// Lets the user pick one of `options` by number. Returns `None` if nothing valid was picked.
+pub fn choose(question: &str, options: &[String]) -> Option<usize> {
+    println!("{}", question);
+    for (i, option) in options.iter().enumerate() {
+        println!("  {}) {}", i + 1, option);
+    }
+    print!("Choose one [1-{}]: ", options.len());
+    io::stdout().flush().expect("Failed to flush stdout.");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).ok()?;
+    match answer.trim().parse::<usize>() {
+        Ok(i) if i >= 1 && i <= options.len() => Some(i - 1),
+        _ => None,
+    }
+}
+
+/// Asks a yes/no question on the command line. Anything but `y` or `yes` counts as no.
+pub fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    io::stdout().flush().expect("Failed to flush stdout.");
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
 }
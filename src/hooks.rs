@@ -0,0 +1,302 @@
+//! User defined commands and webhooks that are run on events,
+//! e.g. when an entry is added or an interaction is logged.
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::error;
+use std::fmt;
+use std::io::{ErrorKind, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::table::{Entry, Interaction};
+
+// a hook that doesn't finish or answer must not hold up the command that triggered it
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct HookFailed {
+    status: std::process::ExitStatus,
+}
+
+impl fmt::Display for HookFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Hook command exited with {}", self.status)
+    }
+}
+
+impl error::Error for HookFailed {
+    fn description(&self) -> &str {
+        "Hook command failed"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HookTimedOut {
+    timeout: Duration,
+}
+
+impl fmt::Display for HookTimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Hook command was stopped after {} second(s)",
+            self.timeout.as_secs_f64()
+        )
+    }
+}
+
+impl error::Error for HookTimedOut {
+    fn description(&self) -> &str {
+        "Hook command timed out"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+/// Waits for `child` to exit, killing it once `timeout` has passed.
+fn wait_timeout(child: &mut Child, timeout: Duration) -> Result<ExitStatus, Box<dyn error::Error>> {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if start.elapsed() >= timeout {
+            // it may have exited just now, then there is nothing to kill
+            let _ = child.kill();
+            child.wait()?;
+            return Err(Box::new(HookTimedOut { timeout }));
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// A shell command and/or URL that receive a JSON payload whenever `event` happens.
+/// `event` is either a full event name like `entry.added`, a prefix like `entry.*`, or `*`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hook {
+    pub event: String,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    event: &'a str,
+    entry: &'a Entry,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interaction: Option<&'a Interaction>,
+}
+
+impl Hook {
+    fn matches(&self, event: &str) -> bool {
+        match self.event.strip_suffix('*') {
+            Some(prefix) => event.starts_with(prefix),
+            None => self.event == event,
+        }
+    }
+
+    /// Runs the command with the payload on stdin, and posts it to the URL.
+    /// Both are given up on after `timeout`.
+    fn call(
+        &self,
+        event: &str,
+        name: &str,
+        payload: &str,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn error::Error>> {
+        if let Some(command) = &self.command {
+            let (shell, flag) = if cfg!(windows) {
+                ("cmd", "/C")
+            } else {
+                ("sh", "-c")
+            };
+            let mut child = Command::new(shell)
+                .arg(flag)
+                .arg(command)
+                .env("KIT_EVENT", event)
+                .env("KIT_NAME", name)
+                .stdin(Stdio::piped())
+                .spawn()?;
+            // written from a thread, so a command that doesn't read a large payload
+            // still times out, and one that closes its stdin early is fine
+            let mut stdin = child.stdin.take().unwrap();
+            let owned = payload.to_string();
+            let writer = thread::spawn(move || stdin.write_all(owned.as_bytes()));
+            let status = wait_timeout(&mut child, timeout)?;
+            match writer.join().expect("Writing the hook payload panicked.") {
+                Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(Box::new(e)),
+                _ => (),
+            }
+            if !status.success() {
+                return Err(Box::new(HookFailed { status }));
+            }
+        }
+        if let Some(url) = &self.url {
+            ureq::AgentBuilder::new()
+                .timeout_connect(timeout)
+                .timeout_read(timeout)
+                .build()
+                .post(url)
+                .set("Content-Type", "application/json")
+                .send_string(payload)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs all hooks registered for `event`. Failing hooks are logged,
+/// but never stop the command that triggered them.
+pub fn run(hooks: &[Hook], event: &str, entry: &Entry, interaction: Option<&Interaction>) {
+    let matching = hooks
+        .iter()
+        .filter(|h| h.matches(event))
+        .collect::<Vec<_>>();
+    if matching.is_empty() {
+        return;
+    }
+    let payload = serde_json::to_string(&Payload {
+        event,
+        entry,
+        interaction,
+    })
+    .expect("Error serializing hook payload.");
+    for hook in matching {
+        match hook.call(event, &entry.name, &payload, TIMEOUT) {
+            Ok(()) => info!("Ran hook for {:?} on {:?}.", event, entry.name),
+            Err(e) => error!("Hook for {:?} on {:?} failed: {}", event, entry.name, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_hook_matches() {
+        let hook = |event: &str| Hook {
+            event: event.to_string(),
+            command: None,
+            url: None,
+        };
+        assert!(hook("entry.added").matches("entry.added"));
+        assert!(!hook("entry.added").matches("entry.due"));
+        assert!(hook("entry.*").matches("entry.due"));
+        assert!(!hook("entry.*").matches("interaction.logged"));
+        assert!(hook("*").matches("interaction.logged"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_hook() {
+        let path =
+            std::env::temp_dir().join(format!("kit-test-command-hook-{}", std::process::id()));
+        let hooks = vec![Hook {
+            event: "entry.*".to_string(),
+            command: Some(format!(
+                "(echo \"$KIT_EVENT $KIT_NAME\"; cat) > {}",
+                path.display()
+            )),
+            url: None,
+        }];
+        let entry = Entry::new("Martin".to_string(), 30, Utc::now());
+        run(&hooks, "entry.added", &entry, None);
+        let output = std::fs::read_to_string(&path).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(Some("entry.added Martin"), lines.next());
+        let payload: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!("entry.added", payload["event"]);
+        assert_eq!("Martin", payload["entry"]["name"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_ignores_payload() {
+        let hook = Hook {
+            event: "*".to_string(),
+            command: Some("true".to_string()),
+            url: None,
+        };
+        let payload = "x".repeat(1 << 20);
+        assert!(hook
+            .call("entry.added", "Martin", &payload, TIMEOUT)
+            .is_ok());
+        let hook = Hook {
+            command: Some("exit 3".to_string()),
+            ..hook
+        };
+        let e = hook
+            .call("entry.added", "Martin", &payload, TIMEOUT)
+            .unwrap_err();
+        assert!(e.is::<HookFailed>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_timeout() {
+        let hook = Hook {
+            event: "*".to_string(),
+            command: Some("sleep 10".to_string()),
+            url: None,
+        };
+        let start = Instant::now();
+        let e = hook
+            .call("entry.added", "Martin", "{}", Duration::from_millis(100))
+            .unwrap_err();
+        assert!(e.is::<HookTimedOut>());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_webhook() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut length = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                line.clear();
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(body).unwrap()
+        });
+        let hooks = vec![Hook {
+            event: "interaction.logged".to_string(),
+            command: None,
+            url: Some(format!("http://127.0.0.1:{}/hook", port)),
+        }];
+        let entry = Entry::new("Martin".to_string(), 30, Utc::now());
+        let interaction = Interaction {
+            date: Utc::now(),
+            channel: "manual".to_string(),
+        };
+        run(&hooks, "interaction.logged", &entry, Some(&interaction));
+        let payload: serde_json::Value = serde_json::from_str(&server.join().unwrap()).unwrap();
+        assert_eq!("manual", payload["interaction"]["channel"]);
+    }
+}
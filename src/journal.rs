@@ -0,0 +1,51 @@
+//! A log of the changes to several entries at once, written next to the table.
+//! Every line is a JSON record with the entries as they were before the change,
+//! so that a mistaken bulk change can be undone by hand.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::table::{Entry, Table};
+use crate::JOURNAL_LOC;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Record {
+    pub date: DateTime<Utc>,
+    pub command: String,
+    pub names: Vec<String>,
+    // the changed entries before the change
+    pub entries: Vec<Entry>,
+    // the changed entries that were suspended before the change
+    pub suspended: Vec<String>,
+}
+
+impl Record {
+    pub fn new(command: &str, names: &[String], before: &Table) -> Self {
+        Record {
+            date: Utc::now(),
+            command: command.to_string(),
+            names: names.to_vec(),
+            entries: names
+                .iter()
+                .filter_map(|n| before.entries.get(n).cloned())
+                .collect(),
+            suspended: names
+                .iter()
+                .filter(|n| before.suspended_entries.contains(*n))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// Appends `record` to the journal next to the table at `table_path`.
+pub fn append(table_path: &Path, record: &Record) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(table_path.with_file_name(JOURNAL_LOC))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+}
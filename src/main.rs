@@ -1,16 +1,40 @@
 use chrono::Local;
-use clap::{crate_version, App, AppSettings, Arg, SubCommand};
 use env_logger::Builder;
 use log::LevelFilter;
 use std::io::Write;
 
+mod actions;
+mod agenda;
+mod cli;
+mod completions;
+mod config;
+mod digest;
+mod edit;
 mod helpers;
+mod hooks;
+mod journal;
+mod names;
+mod notify;
 mod parse;
+mod query;
+mod reminders;
+mod render;
+mod shell;
+mod stats;
+mod status;
 mod subcommands;
+mod sync;
 mod table;
+mod tui;
+mod wizard;
 
 const TABLE_LOC: &str = "table.json";
-const COMPLETION_LOC: &str = "kit-completion.sh";
+const SYNC_STATE_LOC: &str = "sync_state.json";
+const STATUS_LOC: &str = "status.json";
+const NOTIFIED_LOC: &str = "notified.json";
+const CONFIG_LOC: &str = "config.json";
+const SHELL_HISTORY_LOC: &str = "shell_history.txt";
+const JOURNAL_LOC: &str = "journal.jsonl";
 
 fn main() {
     // log time stamp
@@ -27,139 +51,8 @@ fn main() {
         .filter(None, LevelFilter::Info)
         .init();
 
-    let no_update = Arg::with_name("no-autocomplete-update")
-        .long("no-update")
-        .short("n")
-        .global(true)
-        .help(
-            "Disable update of the bash autocomplete script which \
-         is done by default whenver a name is added or changed.",
-        );
-
-    let justtalkedto = SubCommand::with_name("just-talked-to")
-        .about("Set the `last` date of a person to `now`.")
-        .arg(
-            Arg::with_name("name")
-                .required(true)
-                .takes_value(true)
-                .index(1)
-                .help("Name of the person you just talked to."),
-        );
-
-    let add = SubCommand::with_name("add")
-        .about("Add a person to your list.")
-        .arg(
-            Arg::with_name("name")
-                .required(true)
-                .takes_value(true)
-                .index(1)
-                .help("Name of the person you want to add."),
-        )
-        .arg(
-            Arg::with_name("interval")
-                .required(true)
-                .takes_value(true)
-                .index(2)
-                .help("How regularly do you want to talk to the person (in days)?"),
-        )
-        .arg(
-            Arg::with_name("last chat")
-                .required(true)
-                .takes_value(true)
-                .index(3)
-                .help(
-                    "The date of the last chat with your friend. Either `now` or \
-                in a year-month-day format, e.g. `2000-5-4`.",
-                ),
-        );
-
-    let suspend = SubCommand::with_name("suspend")
-        .about(
-            "Suspend an entry. \
-            Inactivates colored highlighting and shows the entry at \
-            the bottom of the table when printed.",
-        )
-        .arg(
-            Arg::with_name("name")
-                .required(true)
-                .takes_value(true)
-                .index(1)
-                .help("Name of the person you want to suspend."),
-        );
-
-    let resume = SubCommand::with_name("resume")
-        .about("Reactivates a suspended entry.")
-        .arg(
-            Arg::with_name("name")
-                .required(true)
-                .takes_value(true)
-                .index(1)
-                .help("Name of the person you want to reactivate."),
-        );
-
-    let remove = SubCommand::with_name("remove")
-        .about("Remove a person from your list.")
-        .arg(
-            Arg::with_name("name")
-                .required(true)
-                .takes_value(true)
-                .index(1)
-                .help("Name of the person you want to remove."),
-        );
-
-    let modify = SubCommand::with_name("modify")
-        .about("Modify an entry.")
-        .arg(
-            Arg::with_name("name")
-                .required(true)
-                .takes_value(true)
-                .index(1)
-                .help("Name of the person you who's entry you want to modify."),
-        )
-        .arg(
-            Arg::with_name("field")
-                .required(true)
-                .takes_value(true)
-                .index(2)
-                .help(
-                    "The entry field you want to modify. \
-                    One of 'name', 'interval', 'last'.",
-                ),
-        )
-        .arg(
-            Arg::with_name("new value")
-                .required(true)
-                .takes_value(true)
-                .index(3)
-                .help("The value you want to replace the existing value with."),
-        );
-
-    let view = SubCommand::with_name("view").about("View the list.");
-
-    let view_active = SubCommand::with_name("view-active").about("View active entries.");
-
-    let view_inactive = SubCommand::with_name("view-inactive").about("View suspended entries.");
-
-    let update_autocompletion = SubCommand::with_name("update-autocompletion")
-        .about("Update the entry names in the kit-completion.sh.");
-
-    let args = App::new("kit")
-        .version(crate_version!())
-        .author("Nick Noel Machnik <nick.machnik@gmail.com>")
-        .about("Command line organizer that helps you remember to call your friends.")
-        .arg(no_update)
-        .subcommand(add)
-        .subcommand(remove)
-        .subcommand(view)
-        .subcommand(modify)
-        .subcommand(justtalkedto)
-        .subcommand(view_active)
-        .subcommand(view_inactive)
-        .subcommand(suspend)
-        .subcommand(resume)
-        .subcommand(update_autocompletion)
-        .setting(AppSettings::ArgRequiredElseHelp)
-        .get_matches();
+    let args = cli::build_cli().get_matches();
+    render::set_color(&args);
 
     match args.subcommand_name() {
         Some("add") => {
@@ -171,9 +64,45 @@ fn main() {
         Some("modify") => {
             subcommands::modify(args);
         }
+        Some("edit") => {
+            subcommands::edit(args);
+        }
+        Some("check") => {
+            subcommands::check(args);
+        }
+        Some("status") => {
+            subcommands::status(args);
+        }
+        Some("notify") => {
+            subcommands::notify(args);
+        }
+        Some("daemon") => {
+            subcommands::daemon(args);
+        }
+        Some("agenda") => {
+            subcommands::agenda(args);
+        }
+        Some("stats") => {
+            subcommands::stats(args);
+        }
+        Some("digest") => {
+            subcommands::digest(args);
+        }
+        Some("install-reminders") => {
+            subcommands::install_reminders(args);
+        }
+        Some("uninstall-reminders") => {
+            subcommands::uninstall_reminders(args);
+        }
         Some("view") => {
             subcommands::view(args);
         }
+        Some("tui") => {
+            subcommands::tui(args);
+        }
+        Some("shell") => {
+            subcommands::shell(args);
+        }
         Some("view-active") => {
             subcommands::view_active(args);
         }
@@ -186,11 +115,20 @@ fn main() {
         Some("resume") => {
             subcommands::resume(args);
         }
+        Some("group") => {
+            subcommands::group(args);
+        }
         Some("just-talked-to") => {
             subcommands::just_talked_to(args);
         }
-        Some("update-autocompletion") => {
-            subcommands::update_autocompletion(args);
+        Some("sync") => {
+            subcommands::sync(args);
+        }
+        Some("completions") => {
+            subcommands::completions(args);
+        }
+        Some("__complete") => {
+            subcommands::complete(args);
         }
         Some(other) => unimplemented!("{}", other),
         None => panic!("what is supposed to happen here"),
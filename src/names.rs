@@ -0,0 +1,394 @@
+//! Lookup of entries by the names typed on the command line.
+//!
+//! A name doesn't have to match exactly. Candidates are searched in
+//! tiers of decreasing certainty and the first tier with any match wins:
+//! exact names, names or aliases that are equal up to case and accents,
+//! unique prefixes and finally names within a small edit distance.
+
+use std::error;
+use std::fmt;
+use strsim::levenshtein;
+
+use crate::table::Table;
+
+/// Lists names as `"A", "B" or "C"`.
+fn quote_names(names: &[String]) -> String {
+    let quoted = names.iter().map(|n| format!("{:?}", n)).collect::<Vec<_>>();
+    match quoted.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+        _ => quoted.join(""),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnknownName {
+    pub name: String,
+    pub suggestions: Vec<String>,
+}
+
+impl fmt::Display for UnknownName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.suggestions.is_empty() {
+            write!(f, "Name {:?} is not in the list.", self.name)
+        } else {
+            write!(
+                f,
+                "Name {:?} is not in the list, did you mean {}?",
+                self.name,
+                quote_names(&self.suggestions)
+            )
+        }
+    }
+}
+
+impl error::Error for UnknownName {
+    fn description(&self) -> &str {
+        "Name not in list"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UncertainName {
+    pub name: String,
+    pub candidate: String,
+}
+
+impl fmt::Display for UncertainName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Name {:?} is not in the list, did you mean {:?}? \
+            Pass `--yes` to use it without a terminal.",
+            self.name, self.candidate
+        )
+    }
+}
+
+impl error::Error for UncertainName {
+    fn description(&self) -> &str {
+        "Name only matches approximately"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AmbiguousName {
+    pub name: String,
+    pub candidates: Vec<String>,
+}
+
+impl fmt::Display for AmbiguousName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Name {:?} is ambiguous, did you mean {}?",
+            self.name,
+            quote_names(&self.candidates)
+        )
+    }
+}
+
+impl error::Error for AmbiguousName {
+    fn description(&self) -> &str {
+        "Name is ambiguous"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+/// The tier in which a name was found.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Match {
+    Exact,
+    /// Equal up to case and accents, or an alias.
+    Equal,
+    Prefix,
+    Typo,
+}
+
+impl Match {
+    /// Whether the match is certain enough to change an entry without asking.
+    pub fn is_certain(self) -> bool {
+        matches!(self, Match::Exact | Match::Equal)
+    }
+}
+
+/// The outcome of looking up a name.
+#[derive(Debug, PartialEq)]
+pub enum Lookup {
+    /// The name of the single entry that matches and how it matched.
+    Found(String, Match),
+    /// Several entries match equally well.
+    Ambiguous(Vec<String>),
+    /// Nothing matches, with names that are close enough to suggest.
+    Missing(Vec<String>),
+}
+
+/// Maps common accented latin letters to their base letters.
+fn fold_char(c: char) -> &'static str {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        _ => "",
+    }
+}
+
+/// Lowercases `name` and removes accents.
+pub fn normalize(name: &str) -> String {
+    let mut res = String::new();
+    for c in name.chars().flat_map(char::to_lowercase) {
+        match fold_char(c) {
+            "" => res.push(c),
+            folded => res.push_str(folded),
+        }
+    }
+    res
+}
+
+/// The largest edit distance at which a name still counts as a typo of the query.
+fn max_distance(query: &str) -> usize {
+    match query.chars().count() {
+        0..=3 => 1,
+        4..=7 => 2,
+        _ => 3,
+    }
+}
+
+/// Sorted and deduplicated names of the matches in one tier.
+fn tier<'a>(names: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut res = names.cloned().collect::<Vec<_>>();
+    res.sort();
+    res.dedup();
+    res
+}
+
+fn decide(mut matches: Vec<String>, how: Match) -> Lookup {
+    if matches.len() == 1 {
+        Lookup::Found(matches.remove(0), how)
+    } else {
+        Lookup::Ambiguous(matches)
+    }
+}
+
+/// The group meant by `query`. Unlike entries, groups only match up to case and accents.
+pub fn lookup_group(table: &Table, query: &str) -> Option<String> {
+    if table.groups.contains_key(query) {
+        return Some(query.to_string());
+    }
+    let normalized = normalize(query);
+    table
+        .groups
+        .keys()
+        .filter(|g| normalize(g) == normalized)
+        .min()
+        .cloned()
+}
+
+/// Looks up the entry that is meant by `query`.
+pub fn lookup(table: &Table, query: &str) -> Lookup {
+    if table.entries.contains_key(query) {
+        return Lookup::Found(query.to_string(), Match::Exact);
+    }
+    let normalized = normalize(query);
+    let equal = tier(table.entries.values().filter_map(|e| {
+        if normalize(&e.name) == normalized || e.aliases.iter().any(|a| normalize(a) == normalized)
+        {
+            Some(&e.name)
+        } else {
+            None
+        }
+    }));
+    if !equal.is_empty() {
+        return decide(equal, Match::Equal);
+    }
+    let prefixed = tier(
+        table
+            .entries
+            .keys()
+            .filter(|n| !normalized.is_empty() && normalize(n).starts_with(&normalized)),
+    );
+    if !prefixed.is_empty() {
+        return decide(prefixed, Match::Prefix);
+    }
+    // typos, the closest names win
+    let mut distances = table
+        .entries
+        .keys()
+        .map(|n| (levenshtein(&normalize(n), &normalized), n))
+        .collect::<Vec<_>>();
+    distances.sort();
+    let max = max_distance(query);
+    match distances.first() {
+        Some((best, _)) if *best <= max => decide(
+            tier(distances.iter().filter(|(d, _)| d == best).map(|(_, n)| *n)),
+            Match::Typo,
+        ),
+        _ => Lookup::Missing(tier(
+            distances
+                .iter()
+                .filter(|(d, _)| *d <= max + 1)
+                .take(3)
+                .map(|(_, n)| *n),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::{Entry, Group};
+    use chrono::Utc;
+
+    fn table() -> Table {
+        let mut table = Table::new();
+        for name in &["Anna", "Annabelle", "José", "Ben Miller", "Ben Meyer"] {
+            table
+                .add_entry(Entry::new(name.to_string(), 7, Utc::now()))
+                .unwrap();
+        }
+        table
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("José Müller"), "jose muller");
+        assert_eq!(normalize("STRAßE"), "strasse");
+    }
+
+    #[test]
+    fn test_exact_match_wins() {
+        assert_eq!(
+            lookup(&table(), "Anna"),
+            Lookup::Found("Anna".to_string(), Match::Exact)
+        );
+    }
+
+    #[test]
+    fn test_case_and_accents() {
+        let table = table();
+        assert_eq!(
+            lookup(&table, "jose"),
+            Lookup::Found("José".to_string(), Match::Equal)
+        );
+        assert_eq!(
+            lookup(&table, "ANNA"),
+            Lookup::Found("Anna".to_string(), Match::Equal)
+        );
+    }
+
+    #[test]
+    fn test_alias() {
+        let mut table = table();
+        table.entries.get_mut("José").unwrap().aliases = vec!["Pepe".to_string()];
+        assert_eq!(
+            lookup(&table, "pepe"),
+            Lookup::Found("José".to_string(), Match::Equal)
+        );
+    }
+
+    #[test]
+    fn test_prefix() {
+        let table = table();
+        assert_eq!(
+            lookup(&table, "annab"),
+            Lookup::Found("Annabelle".to_string(), Match::Prefix)
+        );
+        assert_eq!(
+            lookup(&table, "ben m"),
+            Lookup::Ambiguous(vec!["Ben Meyer".to_string(), "Ben Miller".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_typos() {
+        let table = table();
+        assert_eq!(
+            lookup(&table, "Jsoe"),
+            Lookup::Found("José".to_string(), Match::Typo)
+        );
+        assert_eq!(
+            lookup(&table, "Ben Miler"),
+            Lookup::Found("Ben Miller".to_string(), Match::Typo)
+        );
+        assert_eq!(
+            lookup(&table, "Anabele"),
+            Lookup::Found("Annabelle".to_string(), Match::Typo)
+        );
+    }
+
+    #[test]
+    fn test_messages() {
+        let unknown = UnknownName {
+            name: "Bob".to_string(),
+            suggestions: vec!["Ben".to_string(), "Rob".to_string(), "Bo".to_string()],
+        };
+        assert_eq!(
+            unknown.to_string(),
+            r#"Name "Bob" is not in the list, did you mean "Ben", "Rob" or "Bo"?"#
+        );
+        let ambiguous = AmbiguousName {
+            name: "B".to_string(),
+            candidates: vec!["Ben".to_string()],
+        };
+        assert_eq!(
+            ambiguous.to_string(),
+            r#"Name "B" is ambiguous, did you mean "Ben"?"#
+        );
+    }
+
+    #[test]
+    fn test_groups() {
+        let mut table = table();
+        table
+            .add_group(Group {
+                name: "Band".to_string(),
+                members: vec!["Anna".to_string(), "José".to_string()],
+            })
+            .unwrap();
+        assert_eq!(lookup_group(&table, "band"), Some("Band".to_string()));
+        assert_eq!(lookup_group(&table, "Ban"), None);
+        assert_eq!(lookup_group(&table, "Anna"), None);
+    }
+
+    #[test]
+    fn test_missing() {
+        let table = table();
+        assert_eq!(lookup(&table, "Xavier"), Lookup::Missing(vec![]));
+        assert_eq!(
+            lookup(&table, "Bob Mayr"),
+            Lookup::Missing(vec!["Ben Meyer".to_string()])
+        );
+    }
+}
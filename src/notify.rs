@@ -0,0 +1,156 @@
+//! Desktop notifications for entries that became due.
+
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error;
+use std::fs::{read_to_string, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::table::Table;
+
+/// Something that can show a notification to the user.
+pub trait Notifier {
+    fn notify(&mut self, summary: &str, body: &str) -> Result<(), Box<dyn error::Error>>;
+}
+
+/// Sends freedesktop notifications over D-Bus.
+pub struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    fn notify(&mut self, summary: &str, body: &str) -> Result<(), Box<dyn error::Error>> {
+        notify_rust::Notification::new()
+            .appname("kit")
+            .summary(summary)
+            .body(body)
+            .show()?;
+        Ok(())
+    }
+}
+
+/// Appends notifications to a file, one per line. Useful without a desktop session.
+pub struct FileNotifier {
+    pub path: PathBuf,
+}
+
+impl Notifier for FileNotifier {
+    fn notify(&mut self, summary: &str, body: &str) -> Result<(), Box<dyn error::Error>> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}\t{}", summary, body)?;
+        Ok(())
+    }
+}
+
+/// Remembers for which entries a notification was sent, so that it is sent only once
+/// per due date. The last contact at the time of the notification is stored by name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Sent(HashMap<String, DateTime<Utc>>);
+
+impl Sent {
+    pub fn from_json(path: &Path) -> Result<Self, Box<dyn error::Error>> {
+        let json_file_str = read_to_string(path)?;
+        let data = serde_json::from_str(&json_file_str)?;
+        Ok(data)
+    }
+
+    pub fn to_json(&self, outpath: &Path) {
+        let mut file = BufWriter::new(File::create(outpath).expect("Error when creating outfile."));
+        serde_json::to_writer(&mut file, self).expect("Error writing to outfile.");
+    }
+}
+
+/// Notifies about every active entry that is due and that there was no notification
+/// for since its last contact. Expects up to date remaining times.
+/// Returns the names of the entries that were notified about.
+pub fn notify_due(
+    table: &Table,
+    sent: &mut Sent,
+    notifier: &mut dyn Notifier,
+) -> Result<Vec<String>, Box<dyn error::Error>> {
+    let due = table.due_entries(0);
+    // forget entries that are not due anymore, they get a new notification next time
+    sent.0.retain(|name, _| due.iter().any(|e| &e.name == name));
+    let mut notified = Vec::new();
+    for entry in due {
+        if sent.0.get(&entry.name) == Some(&entry.last_contact) {
+            continue;
+        }
+        notifier.notify(
+            &format!("Time to get in touch with {}", entry.name),
+            &format!(
+                "{}, last contact on {}.",
+                entry.remaining_description(),
+                entry.last_contact.date_naive()
+            ),
+        )?;
+        info!("Sent notification for {:?}.", entry.name);
+        sent.0.insert(entry.name.clone(), entry.last_contact);
+        notified.push(entry.name.clone());
+    }
+    Ok(notified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Entry;
+    use chrono::Duration;
+
+    #[derive(Default)]
+    struct MemoryNotifier(Vec<String>);
+
+    impl Notifier for MemoryNotifier {
+        fn notify(&mut self, summary: &str, _body: &str) -> Result<(), Box<dyn error::Error>> {
+            self.0.push(summary.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_notify_due_only_once() {
+        let mut table = Table::new();
+        let last = Utc::now() - Duration::days(10);
+        table
+            .add_entry(Entry::new("Martin".to_string(), 5, last))
+            .unwrap();
+        table
+            .add_entry(Entry::new("Daniel".to_string(), 30, last))
+            .unwrap();
+        let mut sent = Sent::default();
+        let mut notifier = MemoryNotifier::default();
+        assert_eq!(
+            vec!["Martin"],
+            notify_due(&table, &mut sent, &mut notifier).unwrap()
+        );
+        assert!(notify_due(&table, &mut sent, &mut notifier)
+            .unwrap()
+            .is_empty());
+        assert_eq!(vec!["Time to get in touch with Martin"], notifier.0);
+
+        // a new contact starts a new period
+        let martin = table.entries.get_mut("Martin").unwrap();
+        martin.last_contact = Utc::now() - Duration::days(6);
+        martin.update_remaining_time();
+        assert_eq!(
+            1,
+            notify_due(&table, &mut sent, &mut notifier).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_file_notifier() {
+        let path =
+            std::env::temp_dir().join(format!("kit-test-file-notifier-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut notifier = FileNotifier { path: path.clone() };
+        notifier.notify("a", "b").unwrap();
+        notifier.notify("c", "d").unwrap();
+        assert_eq!("a\tb\nc\td\n", read_to_string(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,44 +1,140 @@
 //! Functions for parsing inputs.
 
-use chrono::{DateTime, TimeZone, Utc};
-use std::convert::TryInto;
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use std::error;
 use std::fmt;
 
 #[derive(Debug, Clone)]
-struct ShortVec;
+pub struct InvalidDate {
+    value: String,
+}
+
+impl fmt::Display for InvalidDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid date {:?}, expected YEAR-MONTH-DAY, `now`, `yesterday` or e.g. `3d ago`",
+            self.value
+        )
+    }
+}
+
+impl error::Error for InvalidDate {
+    fn description(&self) -> &str {
+        "Invalid date"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidInterval {
+    value: String,
+}
+
+impl fmt::Display for InvalidInterval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid interval {:?}, expected a number of days",
+            self.value
+        )
+    }
+}
+
+impl error::Error for InvalidInterval {
+    fn description(&self) -> &str {
+        "Invalid interval"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
 
-impl fmt::Display for ShortVec {
+#[derive(Debug, Clone)]
+pub struct InvalidDuration {
+    value: String,
+}
+
+impl fmt::Display for InvalidDuration {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Expected three elements")
+        write!(
+            f,
+            "Invalid duration {:?}, expected e.g. `3`, `3d` or `2w`",
+            self.value
+        )
     }
 }
 
-impl error::Error for ShortVec {
+impl error::Error for InvalidDuration {
     fn description(&self) -> &str {
-        "Expected three elements"
+        "Invalid duration"
     }
 
-    fn cause(&self) -> Option<&(dyn error::Error)> {
+    fn cause(&self) -> Option<&dyn error::Error> {
         // Generic error, underlying cause isn't tracked.
         None
     }
 }
 
-pub fn parse_date(arg: &str) -> Result<DateTime<Utc>, Box<dyn error::Error>> {
+/// Parses a number of days, weeks (`w`) or days (`d`, the default) into days.
+pub fn parse_duration(arg: &str) -> Result<i64, InvalidDuration> {
+    let arg = arg.trim();
+    let (number, factor) = match arg.char_indices().last() {
+        Some((i, 'w')) => (&arg[..i], 7),
+        Some((i, 'd')) => (&arg[..i], 1),
+        _ => (arg, 1),
+    };
+    match number.parse::<i64>() {
+        Ok(n) => Ok(n * factor),
+        Err(_) => Err(InvalidDuration {
+            value: arg.to_string(),
+        }),
+    }
+}
+
+pub fn parse_interval(arg: &str) -> Result<usize, InvalidInterval> {
+    arg.trim().parse().map_err(|_| InvalidInterval {
+        value: arg.to_string(),
+    })
+}
+
+/// Parses `now` (or `today`), `yesterday`, a duration followed by `ago`
+/// like `3d ago` or `2 weeks ago`, or a YEAR-MONTH-DAY date.
+pub fn parse_date(arg: &str) -> Result<DateTime<Utc>, InvalidDate> {
+    let invalid = || InvalidDate {
+        value: arg.to_string(),
+    };
     match arg {
-        "now" => Ok(Utc::now()),
+        "now" | "today" => Ok(Utc::now()),
+        "yesterday" => Ok(Utc::now() - Duration::days(1)),
+        _ if arg.ends_with(" ago") => {
+            let amount = arg[..arg.len() - 4]
+                .trim()
+                .trim_end_matches('s')
+                .replace(" week", "w")
+                .replace(" day", "d");
+            match parse_duration(&amount) {
+                Ok(days) if days >= 0 => Ok(Utc::now() - Duration::days(days)),
+                _ => Err(invalid()),
+            }
+        }
         _ => {
             let mut split = Vec::new();
             for e in arg.split('-') {
-                split.push(e.parse::<u32>()?);
+                split.push(e.parse::<u32>().map_err(|_| invalid())?);
             }
             if split.len() < 3 {
-                Err(ShortVec.into())
+                Err(invalid())
             } else {
-                Ok(Utc
-                    .ymd(split[0].try_into().unwrap(), split[1], split[2])
-                    .and_hms(12, 12, 12))
+                Utc.with_ymd_and_hms(split[0] as i32, split[1], split[2], 12, 12, 12)
+                    .single()
+                    .ok_or_else(invalid)
             }
         }
     }
@@ -50,7 +146,27 @@ mod tests {
 
     #[test]
     fn test_parse_date_now() {
-        assert_eq!(Utc::now().date(), parse_date("now").unwrap().date());
+        assert_eq!(
+            Utc::now().date_naive(),
+            parse_date("now").unwrap().date_naive()
+        );
+    }
+
+    #[test]
+    fn test_parse_date_relative() {
+        let days_ago = |arg| {
+            let date = parse_date(arg).unwrap();
+            Utc::now().signed_duration_since(date).num_days()
+        };
+        assert_eq!(0, days_ago("today"));
+        assert_eq!(1, days_ago("yesterday"));
+        assert_eq!(3, days_ago("3d ago"));
+        assert_eq!(3, days_ago("3 days ago"));
+        assert_eq!(1, days_ago("1 day ago"));
+        assert_eq!(14, days_ago("2 weeks ago"));
+        assert_eq!(14, days_ago("2w ago"));
+        assert!(parse_date("-2w ago").is_err());
+        assert!(parse_date("2 months ago").is_err());
     }
 
     #[test]
@@ -63,10 +179,31 @@ mod tests {
         assert!(parse_date("baba-05-02").is_err());
     }
 
+    #[test]
+    fn test_parse_date_fail_invalid_day() {
+        assert!(parse_date("2020-02-30").is_err());
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(14, parse_interval(" 14").unwrap());
+        assert!(parse_interval("-3").is_err());
+        assert!(parse_interval("2w").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(3, parse_duration("3").unwrap());
+        assert_eq!(3, parse_duration("3d").unwrap());
+        assert_eq!(14, parse_duration("2w").unwrap());
+        assert!(parse_duration("2m").is_err());
+        assert!(parse_duration("d").is_err());
+    }
+
     #[test]
     fn test_parse_date_custom_date() {
         assert_eq!(
-            Utc.ymd(2020, 5, 2).and_hms(12, 12, 12),
+            Utc.with_ymd_and_hms(2020, 5, 2, 12, 12, 12).unwrap(),
             parse_date("2020-05-02").unwrap()
         );
     }
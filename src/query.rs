@@ -0,0 +1,711 @@
+//! Filtering and sorting of entries for `kit view`.
+//!
+//! A filter is a small expression over the fields of an entry, e.g.
+//! `remaining < 0 and (tag = family or tag = friends)`. Conditions compare a
+//! field with a value and are combined with `and`, `or`, `not` and brackets.
+
+use chrono::NaiveDate;
+use clap::ArgMatches;
+use std::cmp::Ordering;
+use std::error;
+use std::fmt;
+
+use crate::names::normalize;
+use crate::parse::parse_date;
+use crate::table::{Entry, EntryVec, SortField, SortKey, Table};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidQuery {
+    message: String,
+}
+
+impl fmt::Display for InvalidQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid query: {}", self.message)
+    }
+}
+
+impl error::Error for InvalidQuery {
+    fn description(&self) -> &str {
+        "Invalid query"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+fn invalid<T>(message: String) -> Result<T, InvalidQuery> {
+    Err(InvalidQuery { message })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Name,
+    Tag,
+    Notes,
+    Remaining,
+    Interval,
+    Last,
+    Suspended,
+}
+
+const FIELD_NAMES: &str = "name, tag, notes, remaining, interval, last or suspended";
+
+impl Field {
+    fn parse(word: &str) -> Option<Self> {
+        match word {
+            "name" => Some(Field::Name),
+            "tag" | "tags" => Some(Field::Tag),
+            "notes" => Some(Field::Notes),
+            "remaining" => Some(Field::Remaining),
+            "interval" => Some(Field::Interval),
+            "last" => Some(Field::Last),
+            "suspended" => Some(Field::Suspended),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Field::Name => "name",
+            Field::Tag => "tag",
+            Field::Notes => "notes",
+            Field::Remaining => "remaining",
+            Field::Interval => "interval",
+            Field::Last => "last",
+            Field::Suspended => "suspended",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+impl Op {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+            Op::Contains => false,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Contains => "~",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(i64),
+    Date(NaiveDate),
+    Text(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+fn describe(token: Option<&Token>) -> String {
+    match token {
+        None => "the end".to_string(),
+        Some(Token::Word(w)) => format!("{:?}", w),
+        Some(Token::Quoted(w)) => format!("{:?}", w),
+        Some(Token::Op(op)) => format!("{:?}", op.symbol()),
+        Some(Token::Open) => "\"(\"".to_string(),
+        Some(Token::Close) => "\")\"".to_string(),
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, InvalidQuery> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '=' => Token::Op(Op::Eq),
+            '~' => Token::Op(Op::Contains),
+            '!' | '<' | '>' => {
+                let equals = chars.peek() == Some(&'=');
+                if equals {
+                    chars.next();
+                }
+                Token::Op(match (c, equals) {
+                    ('!', true) => Op::Ne,
+                    ('<', false) => Op::Lt,
+                    ('<', true) => Op::Le,
+                    ('>', false) => Op::Gt,
+                    ('>', true) => Op::Ge,
+                    _ => return invalid("expected \"!=\" instead of \"!\"".to_string()),
+                })
+            }
+            '\'' | '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(other) => text.push(other),
+                        None => return invalid(format!("missing closing {}", c)),
+                    }
+                }
+                Token::Quoted(text)
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "()=~!<>'\"".contains(next) {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                Token::Word(word)
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Condition(Field, Op, Value),
+}
+
+/// A parsed filter expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query(Expr);
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, InvalidQuery> {
+        let mut query = self.and()?;
+        while self.keyword("or") {
+            query = Expr::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Expr, InvalidQuery> {
+        let mut query = self.not()?;
+        while self.keyword("and") {
+            query = Expr::And(Box::new(query), Box::new(self.not()?));
+        }
+        Ok(query)
+    }
+
+    fn not(&mut self) -> Result<Expr, InvalidQuery> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.pos += 1;
+            let query = self.or()?;
+            return match self.next() {
+                Some(Token::Close) => Ok(query),
+                other => invalid(format!(
+                    "expected \")\" instead of {}",
+                    describe(other.as_ref())
+                )),
+            };
+        }
+        self.condition()
+    }
+
+    fn condition(&mut self) -> Result<Expr, InvalidQuery> {
+        let field = match self.next() {
+            Some(Token::Word(w)) => match Field::parse(&w.to_lowercase()) {
+                Some(field) => field,
+                None => return invalid(format!("unknown field {:?}, expected {}", w, FIELD_NAMES)),
+            },
+            other => {
+                return invalid(format!(
+                    "expected a field instead of {}",
+                    describe(other.as_ref())
+                ))
+            }
+        };
+        let op = match self.peek() {
+            Some(Token::Op(op)) => *op,
+            // `suspended` on its own is short for `suspended = true`
+            _ if field == Field::Suspended => {
+                return Ok(Expr::Condition(field, Op::Eq, Value::Bool(true)))
+            }
+            other => {
+                return invalid(format!(
+                    "expected a comparison instead of {}",
+                    describe(other)
+                ))
+            }
+        };
+        self.pos += 1;
+        let raw = match self.next() {
+            Some(Token::Word(w)) | Some(Token::Quoted(w)) => w,
+            other => {
+                return invalid(format!(
+                    "expected a value instead of {}",
+                    describe(other.as_ref())
+                ))
+            }
+        };
+        let value = match (field, op) {
+            (Field::Name | Field::Tag | Field::Notes, Op::Eq | Op::Ne | Op::Contains) => {
+                Value::Text(normalize(&raw))
+            }
+            (Field::Name | Field::Tag | Field::Notes, _) => {
+                return invalid(format!(
+                    "{} can only be compared with =, != or ~, not {}",
+                    field.name(),
+                    op.symbol()
+                ))
+            }
+            (Field::Remaining | Field::Interval | Field::Last, Op::Contains) => {
+                return invalid(format!(
+                    "{} can't be searched with ~, use =, !=, <, <=, > or >=",
+                    field.name()
+                ))
+            }
+            (Field::Remaining | Field::Interval, _) => match raw.parse() {
+                Ok(n) => Value::Number(n),
+                Err(_) => return invalid(format!("expected a number instead of {:?}", raw)),
+            },
+            (Field::Last, _) => match parse_date(&raw) {
+                Ok(date) => Value::Date(date.date_naive()),
+                Err(e) => return invalid(e.to_string()),
+            },
+            (Field::Suspended, Op::Eq | Op::Ne) => match raw.as_str() {
+                "true" | "yes" => Value::Bool(true),
+                "false" | "no" => Value::Bool(false),
+                _ => return invalid(format!("expected true or false instead of {:?}", raw)),
+            },
+            (Field::Suspended, _) => {
+                return invalid(format!(
+                    "suspended can only be compared with = or !=, not {}",
+                    op.symbol()
+                ))
+            }
+        };
+        Ok(Expr::Condition(field, op, value))
+    }
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self, InvalidQuery> {
+        let mut parser = Parser {
+            tokens: tokenize(query)?,
+            pos: 0,
+        };
+        let parsed = parser.or()?;
+        match parser.peek() {
+            None => Ok(Query(parsed)),
+            other => invalid(format!(
+                "expected \"and\" or \"or\" instead of {}",
+                describe(other)
+            )),
+        }
+    }
+
+    pub fn matches(&self, table: &Table, entry: &Entry) -> bool {
+        self.0.matches(table, entry)
+    }
+}
+
+impl Expr {
+    fn matches(&self, table: &Table, entry: &Entry) -> bool {
+        match self {
+            Expr::And(a, b) => a.matches(table, entry) && b.matches(table, entry),
+            Expr::Or(a, b) => a.matches(table, entry) || b.matches(table, entry),
+            Expr::Not(a) => !a.matches(table, entry),
+            Expr::Condition(field, op, value) => match (field, value) {
+                (Field::Name, Value::Text(text)) => text_matches(*op, &entry.name, text),
+                (Field::Notes, Value::Text(text)) => {
+                    text_matches(*op, entry.notes.as_deref().unwrap_or(""), text)
+                }
+                (Field::Tag, Value::Text(text)) => {
+                    let tag_op = if *op == Op::Contains {
+                        Op::Contains
+                    } else {
+                        Op::Eq
+                    };
+                    let any = entry.tags.iter().any(|t| text_matches(tag_op, t, text));
+                    if *op == Op::Ne {
+                        !any
+                    } else {
+                        any
+                    }
+                }
+                (Field::Remaining, Value::Number(n)) => op.holds(entry.remaining_time().cmp(n)),
+                (Field::Interval, Value::Number(n)) => op.holds((entry.interval as i64).cmp(n)),
+                (Field::Last, Value::Date(date)) => {
+                    op.holds(entry.last_contact.date_naive().cmp(date))
+                }
+                (Field::Suspended, Value::Bool(b)) => {
+                    op.holds(table.suspended_entries.contains(&entry.name).cmp(b))
+                }
+                _ => unreachable!("conditions are checked when parsing"),
+            },
+        }
+    }
+}
+
+/// Compares text up to case and accents, `~` looks for a part of it.
+fn text_matches(op: Op, text: &str, normalized: &str) -> bool {
+    let text = normalize(text);
+    match op {
+        Op::Eq => text == normalized,
+        Op::Ne => text != normalized,
+        _ => text.contains(normalized),
+    }
+}
+
+/// Parses a comma separated list of fields to sort by, each optionally
+/// followed by `:asc` or `:desc`, e.g. `last:desc,name`.
+pub fn parse_sort(arg: &str) -> Result<Vec<SortKey>, InvalidQuery> {
+    let mut keys = Vec::new();
+    for raw in arg.split(',').map(str::trim) {
+        let (field, order) = match raw.find(':') {
+            Some(i) => (&raw[..i], &raw[i + 1..]),
+            None => (raw, "asc"),
+        };
+        let field = match field {
+            "name" => SortField::Name,
+            "remaining" => SortField::Remaining,
+            "last" => SortField::Last,
+            "interval" => SortField::Interval,
+            other => {
+                return invalid(format!(
+                    "can't sort by {:?}, expected name, remaining, last or interval",
+                    other
+                ))
+            }
+        };
+        let descending = match order {
+            "asc" => false,
+            "desc" => true,
+            other => return invalid(format!("expected asc or desc instead of {:?}", other)),
+        };
+        keys.push(SortKey { field, descending });
+    }
+    Ok(keys)
+}
+
+/// The options of `kit view` that select and order entries.
+#[derive(Debug)]
+pub struct View {
+    filter: Option<Query>,
+    sort: Vec<SortKey>,
+    limit: Option<usize>,
+}
+
+impl View {
+    /// The view asked for, or `None` for the usual list.
+    pub fn from_args(c: &ArgMatches) -> Result<Option<Self>, InvalidQuery> {
+        if !(c.is_present("where") || c.is_present("sort") || c.is_present("limit")) {
+            return Ok(None);
+        }
+        let limit = match c.value_of("limit") {
+            None => None,
+            Some(raw) => match raw.parse() {
+                Ok(limit) => Some(limit),
+                Err(_) => {
+                    return invalid(format!("expected a number as limit instead of {:?}", raw))
+                }
+            },
+        };
+        Ok(Some(View {
+            filter: c.value_of("where").map(Query::parse).transpose()?,
+            sort: c
+                .value_of("sort")
+                .map(parse_sort)
+                .transpose()?
+                .unwrap_or_default(),
+            limit,
+        }))
+    }
+
+    /// The matching entries in order. Like in the usual list, every group is
+    /// a row with the values of its most urgent member and the name of the group.
+    pub fn select(&self, table: &Table) -> EntryVec {
+        let matches = |e: &Entry| self.filter.as_ref().is_none_or(|q| q.matches(table, e));
+        let mut rows: Vec<Entry> = table
+            .entries
+            .values()
+            .filter(|e| matches(e))
+            .cloned()
+            .collect();
+        for group in table.groups.values() {
+            if let Some(member) = table.most_urgent_member(group) {
+                let mut row = member.clone();
+                row.name = group.name.clone();
+                row.aliases.clear();
+                if matches(&row) {
+                    row.name = format!("{} (group)", group.name);
+                    rows.push(row);
+                }
+            }
+        }
+        let mut entries = EntryVec::new(rows);
+        // Ties are broken by the remaining time and then the name,
+        // so the order doesn't depend on how the table is stored.
+        let mut keys = self.sort.clone();
+        keys.push(SortKey {
+            field: SortField::Remaining,
+            descending: false,
+        });
+        keys.push(SortKey {
+            field: SortField::Name,
+            descending: false,
+        });
+        entries.sort_by_keys(&keys);
+        if let Some(limit) = self.limit {
+            entries.truncate(limit);
+        }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Group;
+    use chrono::{Duration, Utc};
+
+    fn table() -> Table {
+        let mut table = Table::new();
+        for (name, interval, days_ago, tags) in &[
+            ("Anna", 7, 10, "family"),
+            ("Ben", 14, 2, "work,school"),
+            ("José", 30, 40, "family,friends"),
+            ("Dan", 60, 0, ""),
+        ] {
+            let mut entry = Entry::new(
+                name.to_string(),
+                *interval,
+                Utc::now() - Duration::days(*days_ago),
+            );
+            entry.tags = tags
+                .split(',')
+                .filter(|t| !t.is_empty())
+                .map(String::from)
+                .collect();
+            table.add_entry(entry).unwrap();
+        }
+        table.suspended_entries.insert("Dan".to_string());
+        table
+    }
+
+    fn names(table: &Table, query: &str) -> Vec<String> {
+        let query = Query::parse(query).unwrap();
+        let mut names: Vec<String> = table
+            .entries
+            .values()
+            .filter(|e| query.matches(table, e))
+            .map(|e| e.name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_conditions() {
+        let table = table();
+        assert_eq!(names(&table, "remaining < 0"), vec!["Anna", "José"]);
+        assert_eq!(
+            names(&table, "remaining < 0 and tag = family"),
+            vec!["Anna", "José"]
+        );
+        assert_eq!(names(&table, "interval >= 30"), vec!["Dan", "José"]);
+        assert_eq!(names(&table, "name = jose"), vec!["José"]);
+        assert_eq!(names(&table, "name ~ AN"), vec!["Anna", "Dan"]);
+        assert_eq!(
+            names(&table, "tag != family and not suspended"),
+            vec!["Ben"]
+        );
+        assert_eq!(
+            names(&table, "suspended = false and tag ~ fri"),
+            vec!["José"]
+        );
+        assert_eq!(names(&table, "last < '3 days ago'"), vec!["Anna", "José"]);
+        assert_eq!(
+            names(&table, "(tag = work or tag = friends) and remaining > -5"),
+            vec!["Ben"]
+        );
+        assert_eq!(
+            names(&table, "not (tag = family or suspended)"),
+            vec!["Ben"]
+        );
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(
+            Query::parse("suspended or tag = a and tag = b").unwrap().0,
+            Expr::Or(
+                Box::new(Expr::Condition(Field::Suspended, Op::Eq, Value::Bool(true))),
+                Box::new(Expr::And(
+                    Box::new(Expr::Condition(
+                        Field::Tag,
+                        Op::Eq,
+                        Value::Text("a".to_string())
+                    )),
+                    Box::new(Expr::Condition(
+                        Field::Tag,
+                        Op::Eq,
+                        Value::Text("b".to_string())
+                    )),
+                )),
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let message = |query| Query::parse(query).unwrap_err().to_string();
+        assert_eq!(
+            message("age > 3"),
+            r#"Invalid query: unknown field "age", expected name, tag, notes, remaining, interval, last or suspended"#
+        );
+        assert_eq!(
+            message("remaining < soon"),
+            r#"Invalid query: expected a number instead of "soon""#
+        );
+        assert_eq!(
+            message("name < b"),
+            r#"Invalid query: name can only be compared with =, != or ~, not <"#
+        );
+        assert_eq!(
+            message("remaining ~ 3"),
+            r#"Invalid query: remaining can't be searched with ~, use =, !=, <, <=, > or >="#
+        );
+        assert_eq!(
+            message("suspended < x"),
+            r#"Invalid query: suspended can only be compared with = or !=, not <"#
+        );
+        assert_eq!(
+            message("(remaining < 0"),
+            r#"Invalid query: expected ")" instead of the end"#
+        );
+        assert_eq!(
+            message("remaining < 0 tag = a"),
+            r#"Invalid query: expected "and" or "or" instead of "tag""#
+        );
+        assert!(message("name = 'Anna").contains("missing closing"));
+        assert!(message("last < tomorrow").contains("Invalid date"));
+    }
+
+    #[test]
+    fn test_sort() {
+        assert_eq!(
+            parse_sort("last:desc, name").unwrap(),
+            vec![
+                SortKey {
+                    field: SortField::Last,
+                    descending: true
+                },
+                SortKey {
+                    field: SortField::Name,
+                    descending: false
+                },
+            ]
+        );
+        assert!(parse_sort("age").is_err());
+        assert!(parse_sort("name:up").is_err());
+    }
+
+    #[test]
+    fn test_select() {
+        let table = table();
+        let view = View {
+            filter: Some(Query::parse("not suspended").unwrap()),
+            sort: parse_sort("last:desc").unwrap(),
+            limit: Some(2),
+        };
+        let selected = view.select(&table);
+        let names: Vec<&str> = selected.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Ben", "Anna"]);
+    }
+
+    #[test]
+    fn test_select_groups() {
+        let mut table = table();
+        table
+            .add_group(Group {
+                name: "Family".to_string(),
+                members: vec!["Anna".to_string(), "José".to_string()],
+            })
+            .unwrap();
+        let names = |query: &str| {
+            let view = View {
+                filter: Some(Query::parse(query).unwrap()),
+                sort: Vec::new(),
+                limit: None,
+            };
+            view.select(&table)
+                .iter()
+                .map(|e| e.name.clone())
+                .collect::<Vec<String>>()
+        };
+        // the group has the values of José, who is overdue by 10 days
+        assert_eq!(names("remaining < -5"), vec!["Family (group)", "José"]);
+        assert_eq!(names("name = family"), vec!["Family (group)"]);
+        assert_eq!(names("name = josé"), vec!["José"]);
+        assert!(names("suspended").iter().all(|n| n == "Dan"));
+    }
+}
@@ -0,0 +1,306 @@
+//! Installs systemd user timers or crontab entries that run
+//! `kit notify` and `kit digest` regularly.
+
+use log::{error, info};
+use std::error;
+use std::fmt;
+use std::fs::{create_dir_all, remove_file, write};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Marks the crontab lines written by kit, so they can be replaced and removed
+const CRON_MARKER: &str = "# kit-reminders";
+
+/// Names of all reminders kit knows about.
+pub const REMINDER_NAMES: &[&str] = &["notify", "digest"];
+
+#[derive(Debug, Clone)]
+pub struct CommandFailed {
+    command: String,
+}
+
+impl fmt::Display for CommandFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Running `{}` failed", self.command)
+    }
+}
+
+impl error::Error for CommandFailed {
+    fn description(&self) -> &str {
+        "Command failed"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+/// A kit command that is run on a schedule.
+#[derive(Debug)]
+pub struct Reminder {
+    // Used for unit names, e.g. `notify` gives `kit-notify.timer`
+    pub name: &'static str,
+    pub description: &'static str,
+    pub args: Vec<&'static str>,
+    // `OnCalendar` expression for systemd, five fields for cron
+    pub schedule: String,
+}
+
+fn command_line(exe: &Path, reminder: &Reminder) -> String {
+    let mut line = format!("'{}'", exe.display().to_string().replace('\'', r"'\''"));
+    for arg in &reminder.args {
+        line.push(' ');
+        line.push_str(arg);
+    }
+    line
+}
+
+fn unit_dir() -> PathBuf {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(".config")
+        });
+    config.join("systemd").join("user")
+}
+
+/// The service and the timer unit for a reminder.
+pub fn systemd_units(exe: &Path, reminder: &Reminder) -> (String, String) {
+    let exec = format!(
+        "\"{}\" {}",
+        exe.display().to_string().replace('"', "\\\""),
+        reminder.args.join(" ")
+    );
+    let service = format!(
+        "[Unit]\n\
+        Description=kit: {description}\n\
+        \n\
+        [Service]\n\
+        Type=oneshot\n\
+        ExecStart={exec}\n",
+        description = reminder.description,
+        exec = exec.trim_end()
+    );
+    let timer = format!(
+        "[Unit]\n\
+        Description=kit: {description}\n\
+        \n\
+        [Timer]\n\
+        OnCalendar={schedule}\n\
+        Persistent=true\n\
+        \n\
+        [Install]\n\
+        WantedBy=timers.target\n",
+        description = reminder.description,
+        schedule = reminder.schedule
+    );
+    (service, timer)
+}
+
+/// Replaces all lines written by kit in `crontab` by `lines`.
+pub fn merge_crontab(crontab: &str, lines: &[String]) -> String {
+    let mut merged = crontab
+        .lines()
+        .filter(|l| !l.ends_with(CRON_MARKER))
+        .map(|l| format!("{}\n", l))
+        .collect::<String>();
+    for line in lines {
+        merged.push_str(&format!("{} {}\n", line, CRON_MARKER));
+    }
+    merged
+}
+
+/// The crontab line for a reminder. Desktop notifications need the session bus,
+/// which cron jobs do not know about, so its current address is passed on.
+pub fn cron_line(exe: &Path, reminder: &Reminder) -> String {
+    let bus = std::env::var("DBUS_SESSION_BUS_ADDRESS")
+        .map(|a| format!("DBUS_SESSION_BUS_ADDRESS='{}' ", a))
+        .unwrap_or_default();
+    format!(
+        "{} {}{}",
+        reminder.schedule,
+        bus,
+        command_line(exe, reminder)
+    )
+}
+
+fn run(program: &str, args: &[&str], input: Option<&str>) -> Result<String, Box<dyn error::Error>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(input) = input {
+        child.stdin.take().unwrap().write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(Box::new(CommandFailed {
+            command: format!("{} {}", program, args.join(" ")),
+        }));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn read_crontab() -> String {
+    // fails if the user has no crontab yet
+    run("crontab", &["-l"], None).unwrap_or_default()
+}
+
+/// The known reminders that aren't in `reminders`.
+fn left_out(reminders: &[Reminder]) -> Vec<&'static str> {
+    REMINDER_NAMES
+        .iter()
+        .filter(|name| !reminders.iter().any(|r| r.name == **name))
+        .cloned()
+        .collect()
+}
+
+/// Writes and enables a systemd timer for every reminder, and removes the
+/// timers of reminders that were installed before but are left out now.
+/// Prints the units instead if `dry_run` is set.
+pub fn install_systemd(reminders: &[Reminder], dry_run: bool) -> Result<(), Box<dyn error::Error>> {
+    let exe = std::env::current_exe()?;
+    let dir = unit_dir();
+    if !dry_run {
+        uninstall_systemd(&left_out(reminders))?;
+    }
+    for reminder in reminders {
+        let (service, timer) = systemd_units(&exe, reminder);
+        let service_path = dir.join(format!("kit-{}.service", reminder.name));
+        let timer_path = dir.join(format!("kit-{}.timer", reminder.name));
+        if dry_run {
+            println!("# {}\n{}", service_path.display(), service);
+            println!("# {}\n{}", timer_path.display(), timer);
+            continue;
+        }
+        create_dir_all(&dir)?;
+        write(&service_path, service)?;
+        write(&timer_path, timer)?;
+        info!("Wrote {:?} and {:?}.", service_path, timer_path);
+    }
+    if dry_run {
+        return Ok(());
+    }
+    let enabled = run("systemctl", &["--user", "daemon-reload"], None).and_then(|_| {
+        for reminder in reminders {
+            let timer = format!("kit-{}.timer", reminder.name);
+            run("systemctl", &["--user", "enable", "--now", &timer], None)?;
+            info!("Enabled {:?}.", timer);
+        }
+        Ok(())
+    });
+    if let Err(e) = enabled {
+        error!(
+            "{}. Enable the timers with `systemctl --user enable --now kit-<name>.timer`.",
+            e
+        );
+    }
+    Ok(())
+}
+
+/// Disables and removes all systemd timers written by `install_systemd`.
+pub fn uninstall_systemd(names: &[&str]) -> Result<(), Box<dyn error::Error>> {
+    let dir = unit_dir();
+    for name in names {
+        let timer = format!("kit-{}.timer", name);
+        let timer_path = dir.join(&timer);
+        if !timer_path.exists() {
+            continue;
+        }
+        if let Err(e) = run("systemctl", &["--user", "disable", "--now", &timer], None) {
+            error!("{}", e);
+        }
+        remove_file(&timer_path)?;
+        remove_file(dir.join(format!("kit-{}.service", name)))?;
+        info!("Removed {:?}.", timer);
+    }
+    // the units are gone either way, a failing reload only leaves stale state behind
+    let _ = run("systemctl", &["--user", "daemon-reload"], None);
+    Ok(())
+}
+
+/// Replaces the kit lines in the crontab of the user by the given reminders.
+/// Prints the new crontab instead if `dry_run` is set.
+pub fn install_cron(reminders: &[Reminder], dry_run: bool) -> Result<(), Box<dyn error::Error>> {
+    let exe = std::env::current_exe()?;
+    let lines = reminders
+        .iter()
+        .map(|r| cron_line(&exe, r))
+        .collect::<Vec<_>>();
+    let crontab = merge_crontab(&read_crontab(), &lines);
+    if dry_run {
+        print!("{}", crontab);
+        return Ok(());
+    }
+    run("crontab", &["-"], Some(&crontab))?;
+    info!("Updated the crontab.");
+    Ok(())
+}
+
+/// Removes all kit lines from the crontab of the user.
+pub fn uninstall_cron() -> Result<(), Box<dyn error::Error>> {
+    let crontab = read_crontab();
+    if !crontab.lines().any(|l| l.ends_with(CRON_MARKER)) {
+        return Ok(());
+    }
+    run("crontab", &["-"], Some(&merge_crontab(&crontab, &[])))?;
+    info!("Removed the reminders from the crontab.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reminder(schedule: &str) -> Reminder {
+        Reminder {
+            name: "notify",
+            description: "notify about due contacts",
+            args: vec!["notify"],
+            schedule: schedule.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_left_out() {
+        assert_eq!(left_out(&[reminder("hourly")]), vec!["digest"]);
+        assert!(left_out(&[
+            reminder("hourly"),
+            Reminder {
+                name: "digest",
+                ..reminder("Mon 09:00")
+            }
+        ])
+        .is_empty());
+    }
+
+    #[test]
+    fn test_systemd_units() {
+        let (service, timer) = systemd_units(Path::new("/opt/my kit/kit"), &reminder("hourly"));
+        assert!(service.contains("ExecStart=\"/opt/my kit/kit\" notify\n"));
+        assert!(timer.contains("OnCalendar=hourly\n"));
+        assert!(timer.contains("WantedBy=timers.target\n"));
+    }
+
+    #[test]
+    fn test_merge_crontab() {
+        let existing = format!(
+            "MAILTO=me\n0 1 * * * backup\n0 * * * * old {}\n",
+            CRON_MARKER
+        );
+        let line = command_line(Path::new("/opt/kit"), &reminder("0 * * * *"));
+        let merged = merge_crontab(&existing, &[format!("0 * * * * {}", line)]);
+        assert_eq!(
+            format!(
+                "MAILTO=me\n0 1 * * * backup\n0 * * * * '/opt/kit' notify {}\n",
+                CRON_MARKER
+            ),
+            merged
+        );
+        assert_eq!("MAILTO=me\n0 1 * * * backup\n", merge_crontab(&merged, &[]));
+    }
+}
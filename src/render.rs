@@ -0,0 +1,682 @@
+//! Renders entries as a table whose columns fit their content and the
+//! width of the terminal.
+
+use chrono::{NaiveDate, Utc};
+use clap::ArgMatches;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::error;
+use std::fmt;
+use std::io::IsTerminal;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::config::ViewConfig;
+use crate::table::{Entry, Table, Urgency};
+
+#[derive(Debug, Clone)]
+pub struct InvalidColumn {
+    name: String,
+}
+
+impl fmt::Display for InvalidColumn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid column {:?}, expected one of {}",
+            self.name,
+            COLUMN_NAMES.join(", ")
+        )
+    }
+}
+
+impl error::Error for InvalidColumn {
+    fn description(&self) -> &str {
+        "Invalid column"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+pub const COLUMN_NAMES: &[&str] = &[
+    "name",
+    "remaining",
+    "last",
+    "interval",
+    "due",
+    "tags",
+    "notes",
+    "urgency",
+    "progress",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Name,
+    Remaining,
+    Last,
+    Interval,
+    Due,
+    Tags,
+    Notes,
+    Urgency,
+    Progress,
+}
+
+const DEFAULT_COLUMNS: &[Column] = &[
+    Column::Name,
+    Column::Remaining,
+    Column::Due,
+    Column::Last,
+    Column::Interval,
+    Column::Progress,
+];
+
+/// Number of characters of the progress bar.
+const BAR_WIDTH: usize = 10;
+
+/// How the remaining time and the last contact are shown.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dates {
+    // e.g. `overdue 4 days` and `3 weeks ago`
+    #[default]
+    Relative,
+    // e.g. `-4` and `2024-03-01`
+    Absolute,
+}
+
+/// Columns are never shrunk below this, unless their content is narrower.
+const MIN_WIDTH: usize = 6;
+
+impl Column {
+    pub fn parse(name: &str) -> Result<Self, InvalidColumn> {
+        match name.trim() {
+            "name" => Ok(Column::Name),
+            "remaining" => Ok(Column::Remaining),
+            "last" => Ok(Column::Last),
+            "interval" => Ok(Column::Interval),
+            "due" => Ok(Column::Due),
+            "tags" => Ok(Column::Tags),
+            "notes" => Ok(Column::Notes),
+            "urgency" => Ok(Column::Urgency),
+            "progress" => Ok(Column::Progress),
+            other => Err(InvalidColumn {
+                name: other.to_string(),
+            }),
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Column::Name => "Name",
+            Column::Remaining => "Remaining",
+            Column::Last => "Last",
+            Column::Interval => "Interval",
+            Column::Due => "Due",
+            Column::Tags => "Tags",
+            Column::Notes => "Notes",
+            Column::Urgency => "Urgency",
+            Column::Progress => "Progress",
+        }
+    }
+
+    /// The value shown for an entry listed under `name`.
+    fn cell(self, name: &str, entry: &Entry, urgency: Urgency, dates: Dates) -> String {
+        match (self, dates) {
+            (Column::Name, _) => name.to_string(),
+            (Column::Remaining, Dates::Relative) => remaining_words(entry.remaining_time()),
+            (Column::Remaining, Dates::Absolute) => entry.remaining_time().to_string(),
+            (Column::Last, Dates::Relative) => {
+                ago_words((Utc::now().date_naive() - entry.last_contact.date_naive()).num_days())
+            }
+            (Column::Last, Dates::Absolute) => entry.last_contact.date_naive().to_string(),
+            (Column::Interval, _) => entry.interval.to_string(),
+            (Column::Due, _) => entry.due_date().to_string(),
+            (Column::Tags, _) => entry.tags.join(", "),
+            // only the first line, the rest is shown by `kit tui`
+            (Column::Notes, _) => entry
+                .notes
+                .as_deref()
+                .and_then(|notes| notes.lines().next())
+                .unwrap_or("")
+                .to_string(),
+            (Column::Urgency, _) => urgency_text(urgency).to_string(),
+            (Column::Progress, _) => progress_bar(entry),
+        }
+    }
+}
+
+/// A number of days in words, in weeks or months where that reads easier.
+fn span(days: i64) -> String {
+    let (n, unit) = if days >= 60 {
+        (days / 30, "month")
+    } else if days >= 14 {
+        (days / 7, "week")
+    } else {
+        (days, "day")
+    };
+    if n == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", n, unit)
+    }
+}
+
+/// The remaining time in words, e.g. `overdue 4 days` or `in 3 weeks`.
+fn remaining_words(remaining: i64) -> String {
+    match remaining {
+        r if r < 0 => format!("overdue {}", span(-r)),
+        0 => "due today".to_string(),
+        1 => "due tomorrow".to_string(),
+        r => format!("in {}", span(r)),
+    }
+}
+
+/// How long ago something was, e.g. `yesterday` or `3 weeks ago`.
+fn ago_words(days: i64) -> String {
+    match days {
+        d if d < 0 => format!("in {}", span(-d)),
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        d => format!("{} ago", span(d)),
+    }
+}
+
+/// How much of the interval has passed, full once the entry is due.
+fn progress_bar(entry: &Entry) -> String {
+    let interval = entry.interval as i64;
+    let elapsed = (interval - entry.remaining_time()).clamp(0, interval);
+    let filled = if interval == 0 {
+        BAR_WIDTH
+    } else {
+        (elapsed * BAR_WIDTH as i64 / interval) as usize
+    };
+    format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled))
+}
+
+/// Marks the urgency in words, for when colors are off or hard to tell apart.
+fn urgency_text(urgency: Urgency) -> &'static str {
+    match urgency {
+        Urgency::Overdue => "!! overdue",
+        Urgency::Soon => "! soon",
+        Urgency::Normal => "",
+        Urgency::Relaxed => "relaxed",
+        Urgency::Suspended => "suspended",
+    }
+}
+
+/// The name of an urgency in machine-readable output.
+fn urgency_name(urgency: Urgency) -> &'static str {
+    match urgency {
+        Urgency::Overdue => "overdue",
+        Urgency::Soon => "soon",
+        Urgency::Normal => "normal",
+        Urgency::Relaxed => "relaxed",
+        Urgency::Suspended => "suspended",
+    }
+}
+
+/// How listings are printed, chosen with `--format`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    pub fn from_args(c: &ArgMatches) -> Self {
+        match c.value_of("format") {
+            Some("json") => Format::Json,
+            _ => Format::Text,
+        }
+    }
+}
+
+/// An entry in the JSON output of the views.
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    name: &'a str,
+    remaining: i64,
+    due: NaiveDate,
+    last: NaiveDate,
+    interval: usize,
+    tags: &'a [String],
+    urgency: &'static str,
+}
+
+/// When to color the output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+/// Whether to color the output. `--color` is followed if given, otherwise
+/// a non-empty `NO_COLOR` turns colors off and a `CLICOLOR_FORCE` other than
+/// `0` turns them on. Without any of these, only terminals get colors.
+fn use_color(
+    choice: ColorChoice,
+    no_color: Option<String>,
+    force: Option<String>,
+    terminal: bool,
+) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            if no_color.is_some_and(|v| !v.is_empty()) {
+                false
+            } else if force.is_some_and(|v| !v.is_empty() && v != "0") {
+                true
+            } else {
+                terminal
+            }
+        }
+    }
+}
+
+/// Turns colors on or off for everything printed from now on, following the
+/// global `--color` option. A bare `--color` is the same as `--color=always`.
+pub fn set_color(args: &ArgMatches) {
+    let choice = match (args.is_present("color"), args.value_of("color")) {
+        (false, _) => ColorChoice::Auto,
+        (true, None) | (true, Some("always")) => ColorChoice::Always,
+        (true, Some("never")) => ColorChoice::Never,
+        (true, Some(_)) => ColorChoice::Auto,
+    };
+    colored::control::set_override(use_color(
+        choice,
+        std::env::var("NO_COLOR").ok(),
+        std::env::var("CLICOLOR_FORCE").ok(),
+        std::io::stdout().is_terminal(),
+    ));
+}
+
+/// How entries are laid out.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    columns: Vec<Column>,
+    borders: bool,
+    dates: Dates,
+    format: Format,
+    // Width available for the table, or `None` if lines may be as long as needed
+    width: Option<usize>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            columns: DEFAULT_COLUMNS.to_vec(),
+            borders: false,
+            dates: Dates::default(),
+            format: Format::Text,
+            width: None,
+        }
+    }
+}
+
+/// The width of the terminal, if there is one. `COLUMNS` takes precedence.
+fn terminal_width() -> Option<usize> {
+    if let Some(columns) = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()) {
+        return Some(columns);
+    }
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    // some pseudo terminals report a width of 0
+    ratatui::crossterm::terminal::size()
+        .ok()
+        .map(|(width, _)| width as usize)
+        .filter(|width| *width > 0)
+}
+
+impl Layout {
+    /// The layout chosen with `--columns`, `--borders`, `--dates` and `--accessible`,
+    /// falling back to the config.
+    pub fn from_args(c: &ArgMatches, config: &ViewConfig) -> Result<Self, InvalidColumn> {
+        let mut columns: Vec<Column> = match c.value_of("columns") {
+            Some(raw) => raw
+                .split(',')
+                .map(Column::parse)
+                .collect::<Result<_, _>>()?,
+            None if !config.columns.is_empty() => config
+                .columns
+                .iter()
+                .map(|name| Column::parse(name))
+                .collect::<Result<_, _>>()?,
+            None => DEFAULT_COLUMNS.to_vec(),
+        };
+        if (c.is_present("accessible") || config.accessible) && !columns.contains(&Column::Urgency)
+        {
+            columns.insert(1.min(columns.len()), Column::Urgency);
+        }
+        Ok(Layout {
+            columns,
+            borders: c.is_present("borders") || config.borders,
+            dates: match c.value_of("dates") {
+                Some("absolute") => Dates::Absolute,
+                Some(_) => Dates::Relative,
+                None => config.dates,
+            },
+            format: Format::from_args(c),
+            width: terminal_width(),
+        })
+    }
+
+    /// Width of everything but the content of the columns.
+    fn decoration_width(&self) -> usize {
+        let n = self.columns.len();
+        if self.borders {
+            3 * n + 1
+        } else {
+            2 * n.saturating_sub(1)
+        }
+    }
+
+    /// Shrinks the widest columns until the table fits.
+    fn fit(&self, mut widths: Vec<usize>) -> Vec<usize> {
+        let available = match self.width {
+            Some(width) => width.saturating_sub(self.decoration_width()),
+            None => return widths,
+        };
+        let minimum: Vec<usize> = widths.iter().map(|w| (*w).min(MIN_WIDTH)).collect();
+        while widths.iter().sum::<usize>() > available {
+            let widest = (0..widths.len())
+                .filter(|i| widths[*i] > minimum[*i])
+                .max_by_key(|i| (widths[*i], std::cmp::Reverse(*i)));
+            match widest {
+                Some(i) => widths[i] -= 1,
+                None => break,
+            }
+        }
+        widths
+    }
+
+    /// Renders a header and one line per row, without colors.
+    fn lines(&self, table: &Table, rows: &[(String, &Entry)]) -> Vec<String> {
+        let header: Vec<String> = self.columns.iter().map(|c| c.title().to_string()).collect();
+        let cells: Vec<Vec<String>> = rows
+            .iter()
+            .map(|(name, e)| {
+                let urgency = table.urgency(e);
+                self.columns
+                    .iter()
+                    .map(|c| c.cell(name, e, urgency, self.dates))
+                    .collect()
+            })
+            .collect();
+        let widths = self.fit(
+            (0..self.columns.len())
+                .map(|i| {
+                    std::iter::once(&header)
+                        .chain(&cells)
+                        .map(|row| row[i].width())
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect(),
+        );
+        let line = |row: &[String]| {
+            let cells = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| pad(&truncate(cell, *width), *width));
+            if self.borders {
+                format!("│ {} │", cells.collect::<Vec<_>>().join(" │ "))
+            } else {
+                cells.collect::<Vec<_>>().join("  ").trim_end().to_string()
+            }
+        };
+        let rule = |left: &str, middle: &str, right: &str| {
+            let parts: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+            format!("{}{}{}", left, parts.join(middle), right)
+        };
+        let mut lines = Vec::new();
+        if self.borders {
+            lines.push(rule("┌", "┬", "┐"));
+        }
+        lines.push(line(&header));
+        if self.borders {
+            lines.push(rule("├", "┼", "┤"));
+        }
+        lines.extend(cells.iter().map(|row| line(row)));
+        if self.borders {
+            lines.push(rule("└", "┴", "┘"));
+        }
+        lines
+    }
+
+    /// Prints the rows, each listed under its name and colored by the urgency of its entry.
+    pub fn print(&self, table: &Table, rows: &[(String, &Entry)]) {
+        if self.format == Format::Json {
+            let records: Vec<Record> = rows
+                .iter()
+                .map(|(name, e)| Record {
+                    name,
+                    remaining: e.remaining_time(),
+                    due: e.due_date(),
+                    last: e.last_contact.date_naive(),
+                    interval: e.interval,
+                    tags: &e.tags,
+                    urgency: urgency_name(table.urgency(e)),
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&records).unwrap());
+            return;
+        }
+        let lines = self.lines(table, rows);
+        let body_start = if self.borders { 2 } else { 1 };
+        for (i, line) in lines.iter().enumerate() {
+            let row = i.checked_sub(body_start).and_then(|i| rows.get(i));
+            let line = match row {
+                None => line.white().on_black().bold(),
+                Some((_, e)) => match table.urgency(e) {
+                    Urgency::Overdue => line.red().on_black(),
+                    Urgency::Soon => line.yellow().on_black(),
+                    Urgency::Relaxed => line.green().on_black(),
+                    Urgency::Suspended => line.truecolor(211, 211, 211).on_black(),
+                    Urgency::Normal => line.magenta().on_black(),
+                },
+            };
+            println!("{}", line);
+        }
+    }
+}
+
+/// Cuts `text` to at most `width` columns, marking the cut with an ellipsis.
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        used += w;
+    }
+    if width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+/// Fills `text` with spaces up to `width` columns.
+pub fn pad(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(text.width())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn entries() -> Vec<Entry> {
+        let date = Utc.with_ymd_and_hms(2020, 3, 20, 12, 12, 12).unwrap();
+        let mut anna = Entry::new("Anna".to_string(), 7, date);
+        anna.tags = vec!["family".to_string(), "friends".to_string()];
+        let long = Entry::new(
+            "Maximilian von Hohenberg-Schwarzenfels".to_string(),
+            30,
+            date,
+        );
+        let wide = Entry::new("李小龍 🐉".to_string(), 14, date);
+        vec![anna, long, wide]
+    }
+
+    fn layout(columns: &[Column], borders: bool, width: Option<usize>) -> Layout {
+        Layout {
+            columns: columns.to_vec(),
+            borders,
+            dates: Dates::Absolute,
+            format: Format::Text,
+            width,
+        }
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("Anna", 4), "Anna");
+        assert_eq!(truncate("Annabelle", 5), "Anna…");
+        assert_eq!(truncate("李小龍", 4), "李…");
+        assert_eq!(truncate("李小龍", 5), "李小…");
+        assert_eq!(pad("李", 4), "李  ");
+    }
+
+    #[test]
+    fn test_sized_by_content() {
+        let entries = entries();
+        let rows: Vec<(String, &Entry)> = entries.iter().map(|e| (e.name.clone(), e)).collect();
+        let lines =
+            layout(&[Column::Name, Column::Interval], false, None).lines(&Table::new(), &rows);
+        assert_eq!(
+            lines,
+            vec![
+                "Name                                    Interval",
+                "Anna                                    7",
+                "Maximilian von Hohenberg-Schwarzenfels  30",
+                "李小龍 🐉                               14",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fit_to_width() {
+        let entries = entries();
+        let rows: Vec<(String, &Entry)> = entries.iter().map(|e| (e.name.clone(), e)).collect();
+        let columns = [Column::Name, Column::Tags, Column::Interval];
+        let lines = layout(&columns, false, Some(30)).lines(&Table::new(), &rows);
+        assert!(lines.iter().all(|l| l.width() <= 30));
+        assert_eq!(
+            lines,
+            vec![
+                "Name       Tags       Interval",
+                "Anna       family, …  7",
+                "Maximili…             30",
+                "李小龍 🐉             14",
+            ]
+        );
+
+        let lines = layout(&columns, true, Some(40)).lines(&Table::new(), &rows);
+        assert_eq!(
+            lines,
+            vec![
+                "┌─────────────┬─────────────┬──────────┐",
+                "│ Name        │ Tags        │ Interval │",
+                "├─────────────┼─────────────┼──────────┤",
+                "│ Anna        │ family, fr… │ 7        │",
+                "│ Maximilian… │             │ 30       │",
+                "│ 李小龍 🐉   │             │ 14       │",
+                "└─────────────┴─────────────┴──────────┘",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_columns() {
+        assert_eq!(Column::parse(" due").unwrap(), Column::Due);
+        assert_eq!(
+            Column::parse("age").unwrap_err().to_string(),
+            "Invalid column \"age\", expected one of name, remaining, last, interval, due, tags, \
+            notes, urgency, progress"
+        );
+        let entry = &entries()[0];
+        assert_eq!(
+            Column::Due.cell("Anna", entry, Urgency::Overdue, Dates::Absolute),
+            "2020-03-27"
+        );
+        assert_eq!(
+            Column::Tags.cell("Anna", entry, Urgency::Overdue, Dates::Absolute),
+            "family, friends"
+        );
+        assert_eq!(
+            Column::Urgency.cell("Anna", entry, Urgency::Overdue, Dates::Absolute),
+            "!! overdue"
+        );
+    }
+
+    #[test]
+    fn test_relative_dates() {
+        assert_eq!(remaining_words(-4), "overdue 4 days");
+        assert_eq!(remaining_words(-1), "overdue 1 day");
+        assert_eq!(remaining_words(0), "due today");
+        assert_eq!(remaining_words(1), "due tomorrow");
+        assert_eq!(remaining_words(13), "in 13 days");
+        assert_eq!(remaining_words(23), "in 3 weeks");
+        assert_eq!(remaining_words(75), "in 2 months");
+        assert_eq!(ago_words(0), "today");
+        assert_eq!(ago_words(1), "yesterday");
+        assert_eq!(ago_words(14), "2 weeks ago");
+
+        let entry = Entry::new("Anna".to_string(), 14, Utc::now() - Duration::days(3));
+        assert_eq!(
+            Column::Remaining.cell("Anna", &entry, Urgency::Normal, Dates::Relative),
+            "in 11 days"
+        );
+        assert_eq!(
+            Column::Last.cell("Anna", &entry, Urgency::Normal, Dates::Relative),
+            "3 days ago"
+        );
+        assert_eq!(
+            Column::Remaining.cell("Anna", &entry, Urgency::Normal, Dates::Absolute),
+            "11"
+        );
+    }
+
+    #[test]
+    fn test_progress_bar() {
+        let bar = |interval, days_ago| {
+            progress_bar(&Entry::new(
+                "Anna".to_string(),
+                interval,
+                Utc::now() - Duration::days(days_ago),
+            ))
+        };
+        assert_eq!(bar(10, 0), "░░░░░░░░░░");
+        assert_eq!(bar(10, 3), "███░░░░░░░");
+        assert_eq!(bar(14, 7), "█████░░░░░");
+        assert_eq!(bar(10, 30), "██████████");
+        assert_eq!(bar(0, 0), "██████████");
+    }
+
+    #[test]
+    fn test_use_color() {
+        let set = |v: &str| Some(v.to_string());
+        assert!(use_color(ColorChoice::Auto, None, None, true));
+        assert!(!use_color(ColorChoice::Auto, None, None, false));
+        assert!(!use_color(ColorChoice::Auto, set("1"), None, true));
+        assert!(use_color(ColorChoice::Auto, set(""), None, true));
+        assert!(use_color(ColorChoice::Auto, None, set("1"), false));
+        assert!(!use_color(ColorChoice::Auto, None, set("0"), false));
+        assert!(!use_color(ColorChoice::Auto, set("1"), set("1"), true));
+        assert!(use_color(ColorChoice::Always, set("1"), None, false));
+        assert!(!use_color(ColorChoice::Never, None, set("1"), true));
+    }
+}
@@ -0,0 +1,296 @@
+//! An interactive shell that runs subcommands on a table that is loaded once.
+//!
+//! Changes to the table go through `actions` and are saved right away.
+//! Views are printed from the loaded table. All other subcommands run
+//! as a separate `kit` process, after which the table is read again.
+
+use clap::ErrorKind;
+use log::error;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+
+use crate::actions;
+use crate::cli::{build_cli, subcommand_names, FIELDS, MULTI_NAME_SUBCOMMANDS, NAME_SUBCOMMANDS};
+use crate::completions;
+use crate::helpers::get_view_config;
+use crate::query::View;
+use crate::render::{self, Layout};
+use crate::table::Table;
+
+const EXIT: &[&str] = &["exit", "quit"];
+
+/// Completes subcommands, names and fields.
+struct ShellHelper {
+    commands: Vec<String>,
+    names: Vec<String>,
+}
+
+/// Splits a line into words like a shell does. Returns the finished words,
+/// and the start and unquoted text of the word that is still being typed.
+fn split_partial(line: &str) -> (Vec<String>, usize, String) {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut start = None;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if escaped {
+            current.push(c);
+            escaped = false;
+            continue;
+        }
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, c) if c.is_whitespace() => {
+                if start.take().is_some() {
+                    words.push(std::mem::take(&mut current));
+                }
+            }
+            (None, c) => {
+                start.get_or_insert(i);
+                match c {
+                    '\\' => escaped = true,
+                    '\'' | '"' => quote = Some(c),
+                    c => current.push(c),
+                }
+            }
+        }
+    }
+    (words, start.unwrap_or(line.len()), current)
+}
+
+impl ShellHelper {
+    /// The start of the completed word and its candidates, quoted where needed.
+    fn candidates(&self, line: &str) -> (usize, Vec<String>) {
+        let (words, start, partial) = split_partial(line);
+        let options: Vec<&str> = match words.iter().map(|w| w.as_str()).collect::<Vec<_>>()[..] {
+            [] => self
+                .commands
+                .iter()
+                .map(|c| c.as_str())
+                .chain(EXIT.iter().cloned())
+                .collect(),
+            [command] if NAME_SUBCOMMANDS.contains(&command) => {
+                self.names.iter().map(|n| n.as_str()).collect()
+            }
+            ["modify", _] => FIELDS.to_vec(),
+            [command, .., last]
+                if MULTI_NAME_SUBCOMMANDS.contains(&command) && !last.starts_with('-') =>
+            {
+                self.names.iter().map(|n| n.as_str()).collect()
+            }
+            _ => Vec::new(),
+        };
+        let candidates = options
+            .into_iter()
+            .filter(|o| o.starts_with(&partial))
+            .map(|o| shell_words::quote(o).into_owned())
+            .collect();
+        (start, candidates)
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(&line[..pos]))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// Runs one line of input. Errors of the command are returned,
+/// the table stays as it was before the failed command.
+fn execute(data: &mut Table, table_path: &Path, words: &[String]) -> Result<(), Box<dyn Error>> {
+    let args = build_cli()
+        .get_matches_from_safe(std::iter::once("kit").chain(words.iter().map(|w| w.as_str())));
+    let args = match args {
+        Ok(args) => args,
+        Err(e) if e.kind == ErrorKind::HelpDisplayed || e.kind == ErrorKind::VersionDisplayed => {
+            println!("{}", e.message);
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("{}", e.message);
+            return Ok(());
+        }
+    };
+    render::set_color(&args);
+    match args.subcommand() {
+        ("add", Some(c)) => actions::add(data, table_path, c),
+        ("remove", Some(c)) => actions::remove(data, table_path, c),
+        ("modify", Some(c)) => actions::modify(data, table_path, c),
+        ("just-talked-to", Some(c)) => actions::just_talked_to(data, table_path, c),
+        ("suspend", Some(c)) => actions::suspend(data, table_path, c),
+        ("resume", Some(c)) => actions::resume(data, table_path, c),
+        ("group", Some(c)) => actions::group(data, table_path, c),
+        ("edit", Some(c)) => actions::edit(data, table_path, c),
+        ("sync", Some(c)) => actions::sync(data, table_path, c),
+        ("view", Some(c)) | ("view-active", Some(c)) | ("view-inactive", Some(c)) => {
+            let view = match args.subcommand_name() {
+                Some("view") => View::from_args(c)?,
+                _ => None,
+            };
+            let layout = Layout::from_args(c, &get_view_config())?;
+            data.update_entries_par();
+            match (args.subcommand_name(), view) {
+                (_, Some(view)) => data.print_entries(&view.select(data), &layout),
+                (Some("view-active"), _) => data.print_active_by_remaining_time(&layout),
+                (Some("view-inactive"), _) => data.print_inactive_by_remaining_time(&layout),
+                _ => data.print_all_by_remaining_time(&layout),
+            }
+            Ok(())
+        }
+        ("shell", _) => {
+            println!("Already in the shell.");
+            Ok(())
+        }
+        _ => {
+            Command::new(std::env::current_exe()?)
+                .args(words)
+                .status()?;
+            if table_path.exists() {
+                *data = Table::from_json(table_path)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Reads and runs commands until `exit` or the end of input.
+pub fn run(data: &mut Table, table_path: &Path, history_path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut editor = Editor::<ShellHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(ShellHelper {
+        commands: subcommand_names(),
+        names: completions::names(data, ""),
+    }));
+    // there is no history before the first session
+    let _ = editor.load_history(history_path);
+    loop {
+        let line = match editor.readline("kit> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(Box::new(e)),
+        };
+        editor.add_history_entry(line.as_str())?;
+        let words = match shell_words::split(&line) {
+            Ok(words) => words,
+            Err(e) => {
+                error!("{}", e);
+                continue;
+            }
+        };
+        match words.first().map(|w| w.as_str()) {
+            None => continue,
+            Some(word) if EXIT.contains(&word) => break,
+            Some(_) => {}
+        }
+        if let Err(e) = execute(data, table_path, &words) {
+            error!("{}", e);
+        }
+        editor.helper_mut().unwrap().names = completions::names(data, "");
+    }
+    editor.save_history(history_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn helper() -> ShellHelper {
+        ShellHelper {
+            commands: subcommand_names(),
+            names: vec!["Anna".to_string(), "Ben O'Brien".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_split_partial() {
+        assert_eq!(
+            split_partial("modify 'Ben O'\\''Brien' in"),
+            (
+                vec!["modify".to_string(), "Ben O'Brien".to_string()],
+                24,
+                "in".to_string()
+            )
+        );
+        assert_eq!(
+            split_partial("remove \"Ben O"),
+            (vec!["remove".to_string()], 7, "Ben O".to_string())
+        );
+        assert_eq!(
+            split_partial("remove "),
+            (vec!["remove".to_string()], 7, String::new())
+        );
+    }
+
+    #[test]
+    fn test_complete_commands() {
+        let (start, candidates) = helper().candidates("vi");
+        assert_eq!(start, 0);
+        assert_eq!(candidates, vec!["view", "view-active", "view-inactive"]);
+        assert_eq!(helper().candidates("ex").1, vec!["exit"]);
+    }
+
+    #[test]
+    fn test_complete_names() {
+        let (start, candidates) = helper().candidates("suspend B");
+        assert_eq!(start, 8);
+        assert_eq!(candidates, vec![r"'Ben O'\''Brien'"]);
+        assert_eq!(helper().candidates("remove 'Ben").1.len(), 1);
+        assert!(helper().candidates("add B").1.is_empty());
+        assert_eq!(helper().candidates("suspend Anna B").1.len(), 1);
+        assert!(helper().candidates("suspend --tag B").1.is_empty());
+        assert!(helper().candidates("modify Anna B").1.is_empty());
+    }
+
+    #[test]
+    fn test_complete_fields() {
+        assert_eq!(helper().candidates("modify Anna in").1, vec!["interval"]);
+    }
+
+    #[test]
+    fn test_execute() {
+        let dir = std::env::temp_dir().join(format!("kit-test-shell-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("table.json");
+        let mut data = Table::new();
+        let line = |l: &str| shell_words::split(l).unwrap();
+        execute(&mut data, &path, &line("add 'Ben O'\\''Brien' 7 now")).unwrap();
+        execute(&mut data, &path, &line("modify ben interval 14 --yes")).unwrap();
+        assert!(execute(&mut data, &path, &line("modify ben interval x --yes")).is_err());
+        // clap errors are printed, not returned
+        execute(&mut data, &path, &line("modify ben")).unwrap();
+        assert_eq!(data.entries["Ben O'Brien"].interval, 14);
+        assert_eq!(
+            Table::from_json(&path).unwrap().entries["Ben O'Brien"].interval,
+            14
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
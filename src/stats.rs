@@ -0,0 +1,464 @@
+//! How well contacts keep to their intervals, computed from the recorded history.
+//!
+//! The history of an entry are the days of its recorded interactions and its
+//! last contact. Gaps between them are compared with the current interval,
+//! since earlier intervals aren't recorded.
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use unicode_width::UnicodeWidthStr;
+
+use crate::render::pad;
+use crate::table::{Entry, Table};
+
+/// Width of the longest bar in the contacts per week.
+const BAR_WIDTH: usize = 30;
+
+/// Number of tags listed as most neglected.
+const TAG_COUNT: usize = 5;
+
+/// The days with contact, oldest first, each with the channel it was first recorded from.
+fn history(entry: &Entry) -> Vec<(NaiveDate, Option<&str>)> {
+    let mut days: BTreeMap<NaiveDate, Option<&str>> = BTreeMap::new();
+    for interaction in &entry.interactions {
+        days.entry(interaction.date.date_naive())
+            .or_insert(Some(&interaction.channel));
+    }
+    days.entry(entry.last_contact.date_naive()).or_insert(None);
+    days.into_iter().collect()
+}
+
+fn percent(fraction: Option<f64>) -> String {
+    match fraction {
+        Some(f) => format!("{:.0}%", f * 100.0),
+        None => "-".to_string(),
+    }
+}
+
+/// The share of gaps that were on time, given as (on time, all).
+fn share((on_time, all): (usize, usize)) -> Option<f64> {
+    if all == 0 {
+        None
+    } else {
+        Some(on_time as f64 / all as f64)
+    }
+}
+
+/// Adds up (on time, all) counts of gaps.
+fn total(counts: impl Iterator<Item = (usize, usize)>) -> (usize, usize) {
+    counts.fold((0, 0), |(a, b), (c, d)| (a + c, b + d))
+}
+
+fn days(n: i64) -> String {
+    match n {
+        0 => "-".to_string(),
+        1 => "1 day".to_string(),
+        n => format!("{} days", n),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PersonStats {
+    pub name: String,
+    pub interval: usize,
+    pub contacts: usize,
+    // Mean number of days between two contacts
+    pub average_gap: Option<f64>,
+    // Share of gaps that were at most the interval
+    pub on_time: Option<f64>,
+    // Most days past the interval, including the time since the last contact
+    pub longest_overdue: i64,
+    // Gaps that were on time and all gaps, to weigh entries by their number of gaps
+    #[serde(skip)]
+    counted: (usize, usize),
+}
+
+impl PersonStats {
+    fn from_entry(entry: &Entry, today: NaiveDate) -> Self {
+        let days: Vec<NaiveDate> = history(entry).into_iter().map(|(day, _)| day).collect();
+        let gaps: Vec<i64> = days.windows(2).map(|w| (w[1] - w[0]).num_days()).collect();
+        let interval = entry.interval as i64;
+        let open_gap = days.last().map_or(0, |last| (today - *last).num_days());
+        let on_time = gaps.iter().filter(|g| **g <= interval).count();
+        PersonStats {
+            name: entry.name.clone(),
+            interval: entry.interval,
+            contacts: days.len(),
+            average_gap: if gaps.is_empty() {
+                None
+            } else {
+                Some(gaps.iter().sum::<i64>() as f64 / gaps.len() as f64)
+            },
+            on_time: share((on_time, gaps.len())),
+            longest_overdue: gaps
+                .iter()
+                .chain(std::iter::once(&open_gap))
+                .map(|g| g - interval)
+                .max()
+                .unwrap_or(0)
+                .max(0),
+            counted: (on_time, gaps.len()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagStats {
+    pub tag: String,
+    pub entries: usize,
+    pub overdue: usize,
+    // Share of the gaps of all entries with the tag that were on time
+    pub on_time: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeekStats {
+    // The Monday the week starts on
+    pub week: NaiveDate,
+    pub contacts: usize,
+}
+
+/// Statistics about the active entries.
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub on_time: Option<f64>,
+    pub people: Vec<PersonStats>,
+    pub tags: Vec<TagStats>,
+    pub weeks: Vec<WeekStats>,
+}
+
+impl Stats {
+    pub fn from_table(table: &Table, today: NaiveDate, weeks: usize) -> Self {
+        let active: Vec<&Entry> = table
+            .entries
+            .values()
+            .filter(|e| !table.suspended_entries.contains(&e.name))
+            .collect();
+
+        let mut people: Vec<PersonStats> = active
+            .iter()
+            .map(|e| PersonStats::from_entry(e, today))
+            .collect();
+        // the least punctual first, people without gaps last
+        people.sort_by(|a, b| {
+            let key = |p: &PersonStats| p.on_time.unwrap_or(f64::INFINITY);
+            key(a)
+                .partial_cmp(&key(b))
+                .unwrap()
+                .then_with(|| b.longest_overdue.cmp(&a.longest_overdue))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        // the entries with each tag together with their stats
+        let mut by_tag: BTreeMap<String, Vec<(&Entry, &PersonStats)>> = BTreeMap::new();
+        for p in &people {
+            let e = &table.entries[&p.name];
+            for tag in &e.tags {
+                by_tag.entry(tag.to_lowercase()).or_default().push((e, p));
+            }
+        }
+        let mut tags: Vec<TagStats> = by_tag
+            .into_iter()
+            .map(|(tag, entries)| TagStats {
+                tag,
+                entries: entries.len(),
+                overdue: entries
+                    .iter()
+                    .filter(|(e, _)| e.remaining_time() < 0)
+                    .count(),
+                on_time: share(total(entries.iter().map(|(_, p)| p.counted))),
+            })
+            .collect();
+        tags.sort_by(|a, b| {
+            let key = |t: &TagStats| t.on_time.unwrap_or(f64::INFINITY);
+            key(a)
+                .partial_cmp(&key(b))
+                .unwrap()
+                .then_with(|| b.overdue.cmp(&a.overdue))
+                .then_with(|| a.tag.cmp(&b.tag))
+        });
+        tags.truncate(TAG_COUNT);
+
+        let this_monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let mut weeks: Vec<WeekStats> = (0..weeks as i64)
+            .rev()
+            .map(|i| WeekStats {
+                week: this_monday - Duration::weeks(i),
+                contacts: 0,
+            })
+            .collect();
+        for e in &active {
+            for (day, _) in history(e) {
+                if let Some(week) = weeks
+                    .iter_mut()
+                    .find(|w| day >= w.week && day < w.week + Duration::weeks(1))
+                {
+                    week.contacts += 1;
+                }
+            }
+        }
+
+        let overall = total(people.iter().map(|p| p.counted));
+        Stats {
+            on_time: share(overall),
+            people,
+            tags,
+            weeks,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = match self.on_time {
+            Some(_) => format!(
+                "{} of the gaps between contacts were on time\n",
+                percent(self.on_time)
+            ),
+            None => "There are no gaps between recorded contacts yet\n".to_string(),
+        };
+
+        let name_width = self
+            .people
+            .iter()
+            .map(|p| p.name.width())
+            .chain(std::iter::once("Name".len()))
+            .max()
+            .unwrap();
+        text.push_str(&format!(
+            "\n{}  Contacts  Average gap  Interval  On time  Longest overdue\n",
+            pad("Name", name_width)
+        ));
+        for p in &self.people {
+            let average = match p.average_gap {
+                Some(gap) => format!("{:.1} days", gap),
+                None => "-".to_string(),
+            };
+            text.push_str(&format!(
+                "{}  {: <8}  {: <11}  {: <8}  {: <7}  {}\n",
+                pad(&p.name, name_width),
+                p.contacts,
+                average,
+                days(p.interval as i64),
+                percent(p.on_time),
+                days(p.longest_overdue),
+            ));
+        }
+
+        if !self.tags.is_empty() {
+            text.push_str("\nMost neglected tags\n");
+            for t in &self.tags {
+                text.push_str(&format!(
+                    "  {}: {} on time, {} of {} overdue\n",
+                    t.tag,
+                    percent(t.on_time),
+                    t.overdue,
+                    t.entries
+                ));
+            }
+        }
+
+        text.push_str("\nContacts per week\n");
+        let most = self.weeks.iter().map(|w| w.contacts).max().unwrap_or(0);
+        for w in &self.weeks {
+            let bar = if most == 0 {
+                0
+            } else {
+                (w.contacts * BAR_WIDTH).div_ceil(most)
+            };
+            text.push_str(&format!(
+                "  {}  {} {}\n",
+                w.week.format("%b %d"),
+                "█".repeat(bar),
+                w.contacts
+            ));
+        }
+        text
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Contact<'a> {
+    pub date: NaiveDate,
+    pub channel: Option<&'a str>,
+    // Days since the contact before, if there was one
+    pub gap: Option<i64>,
+    // Days past the interval, 0 if the contact was on time
+    pub late_by: i64,
+}
+
+/// Every contact with a person and how punctual it was.
+#[derive(Debug, Serialize)]
+pub struct PersonHistory<'a> {
+    #[serde(flatten)]
+    pub stats: PersonStats,
+    pub history: Vec<Contact<'a>>,
+}
+
+impl<'a> PersonHistory<'a> {
+    pub fn from_entry(entry: &'a Entry, today: NaiveDate) -> Self {
+        let mut previous: Option<NaiveDate> = None;
+        let history = history(entry)
+            .into_iter()
+            .map(|(date, channel)| {
+                let gap = previous.map(|p| (date - p).num_days());
+                previous = Some(date);
+                Contact {
+                    date,
+                    channel,
+                    gap,
+                    late_by: gap.map_or(0, |g| (g - entry.interval as i64).max(0)),
+                }
+            })
+            .collect();
+        PersonHistory {
+            stats: PersonStats::from_entry(entry, today),
+            history,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let s = &self.stats;
+        let mut text = format!(
+            "{}: every {}, {} contact(s)\n",
+            s.name,
+            days(s.interval as i64),
+            s.contacts
+        );
+        if let Some(gap) = s.average_gap {
+            text.push_str(&format!(
+                "Average gap {:.1} days, {} on time, longest overdue {}\n",
+                gap,
+                percent(s.on_time),
+                days(s.longest_overdue)
+            ));
+        }
+        text.push('\n');
+        for c in &self.history {
+            let gap = match c.gap {
+                None => "first".to_string(),
+                Some(_) if c.late_by > 0 => format!("{} late", days(c.late_by)),
+                Some(g) => format!("after {}", days(g)),
+            };
+            text.push_str(&format!(
+                "  {}  {: <16}  {}\n",
+                c.date,
+                gap,
+                c.channel.unwrap_or("")
+            ));
+        }
+        text
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Interaction;
+    use chrono::{TimeZone, Utc};
+
+    fn at(m: u32, d: u32) -> chrono::DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, m, d, 12, 0, 0).unwrap()
+    }
+
+    fn entry(name: &str, interval: usize, days: &[(u32, u32)], tags: &[&str]) -> Entry {
+        let mut entry = Entry::new(name.to_string(), interval, at(days[0].0, days[0].1));
+        for (m, d) in days {
+            entry.record_interaction(Interaction {
+                date: at(*m, *d),
+                channel: "manual".to_string(),
+            });
+        }
+        entry.tags = tags.iter().map(|t| t.to_string()).collect();
+        entry
+    }
+
+    fn table() -> Table {
+        let mut table = Table::new();
+        // gaps of 7, 10 and 5 days with an interval of 7
+        table
+            .add_entry(entry(
+                "Anna",
+                7,
+                &[(3, 1), (3, 8), (3, 18), (3, 23)],
+                &["family"],
+            ))
+            .unwrap();
+        // gaps of 20 and 30 days with an interval of 14
+        table
+            .add_entry(entry("Ben", 14, &[(2, 1), (2, 21), (3, 22)], &["Work"]))
+            .unwrap();
+        table
+            .add_entry(entry("Carl", 30, &[(3, 20)], &["work"]))
+            .unwrap();
+        table
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 27).unwrap()
+    }
+
+    #[test]
+    fn test_people() {
+        let stats = Stats::from_table(&table(), today(), 4);
+        let names: Vec<&str> = stats.people.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["Ben", "Anna", "Carl"]);
+        let anna = &stats.people[1];
+        assert_eq!(anna.contacts, 4);
+        assert!((anna.average_gap.unwrap() - 22.0 / 3.0).abs() < 1e-9);
+        assert!((anna.on_time.unwrap() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(anna.longest_overdue, 3);
+        assert_eq!(stats.people[0].on_time, Some(0.0));
+        assert_eq!(stats.people[0].longest_overdue, 16);
+        assert_eq!(stats.people[2].on_time, None);
+        assert!((stats.on_time.unwrap() - 0.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tags_and_weeks() {
+        let mut table = table();
+        // suspended entries count nowhere
+        table
+            .add_entry(entry("Dan", 7, &[(3, 5), (3, 19)], &["work"]))
+            .unwrap();
+        table.suspended_entries.insert("Dan".to_string());
+        let stats = Stats::from_table(&table, today(), 4);
+        assert_eq!(stats.tags[0].tag, "work");
+        assert_eq!(stats.tags[0].entries, 2);
+        assert_eq!(stats.tags[0].on_time, Some(0.0));
+        assert_eq!(stats.tags[1].tag, "family");
+        let weeks: Vec<(u32, usize)> = stats
+            .weeks
+            .iter()
+            .map(|w| (w.week.day(), w.contacts))
+            .collect();
+        // Anna on 3-8, 3-18 and 3-23, Ben on 3-22 and Carl on 3-20
+        assert_eq!(weeks, vec![(4, 1), (11, 0), (18, 4), (25, 0)]);
+        assert!(stats
+            .to_text()
+            .contains("  Mar 18  ██████████████████████████████ 4\n"));
+    }
+
+    #[test]
+    fn test_person_history() {
+        let table = table();
+        let history = PersonHistory::from_entry(&table.entries["Anna"], today());
+        let lates: Vec<(Option<i64>, i64)> =
+            history.history.iter().map(|c| (c.gap, c.late_by)).collect();
+        assert_eq!(
+            lates,
+            vec![(None, 0), (Some(7), 0), (Some(10), 3), (Some(5), 0)]
+        );
+        let text = history.to_text();
+        assert!(text.starts_with("Anna: every 7 days, 4 contact(s)\n"));
+        assert!(text.contains("  2024-03-18  3 days late       manual\n"));
+        assert!(history.to_json().contains(r#""late_by": 3"#));
+    }
+}
@@ -0,0 +1,122 @@
+//! A small summary of the table that is written next to it on every save.
+//! It is cheap to read, which makes it usable for shell prompts.
+
+use chrono::NaiveDate;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::error;
+use std::fs::{read_to_string, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::table::{days_until, Table};
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    // the due date of every active entry
+    due: Vec<NaiveDate>,
+}
+
+/// Numbers of active entries by urgency.
+#[derive(Debug, PartialEq)]
+pub struct Counts {
+    pub overdue: usize,
+    pub today: usize,
+    pub week: usize,
+    pub active: usize,
+}
+
+impl Summary {
+    pub fn from_table(table: &Table) -> Self {
+        Summary {
+            due: table
+                .entries
+                .values()
+                .filter(|e| !table.suspended_entries.contains(&e.name))
+                .map(|e| e.due_date())
+                .collect(),
+        }
+    }
+
+    pub fn from_json(path: &Path) -> Result<Self, Box<dyn error::Error>> {
+        let json_file_str = read_to_string(path)?;
+        let data = serde_json::from_str(&json_file_str)?;
+        Ok(data)
+    }
+
+    pub fn to_json(&self, outpath: &Path) {
+        let mut file = BufWriter::new(File::create(outpath).expect("Error when creating outfile."));
+        serde_json::to_writer(&mut file, self).expect("Error writing to outfile.");
+    }
+
+    /// Counts the entries by their remaining time on `today`.
+    pub fn counts(&self, today: NaiveDate) -> Counts {
+        let remaining = self
+            .due
+            .iter()
+            .map(|due| days_until(*due, today))
+            .collect::<Vec<_>>();
+        Counts {
+            overdue: remaining.iter().filter(|r| **r < 0).count(),
+            today: remaining.iter().filter(|r| **r == 0).count(),
+            week: remaining.iter().filter(|r| (0..7).contains(*r)).count(),
+            active: remaining.len(),
+        }
+    }
+}
+
+impl Counts {
+    /// Fills `{overdue}`, `{today}`, `{week}` and `{active}` into `template`.
+    pub fn render(&self, template: &str) -> String {
+        template
+            .replace("{overdue}", &self.overdue.to_string())
+            .replace("{today}", &self.today.to_string())
+            .replace("{week}", &self.week.to_string())
+            .replace("{active}", &self.active.to_string())
+    }
+
+    /// Colors `text` by the most urgent of the counts.
+    pub fn colorize(&self, text: String) -> String {
+        if self.overdue > 0 {
+            text.red().to_string()
+        } else if self.today > 0 {
+            text.yellow().to_string()
+        } else {
+            text.green().to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Entry;
+    use chrono::{Duration, Utc};
+
+    #[test]
+    fn test_counts() {
+        let now = Utc::now();
+        let mut table = Table::new();
+        for (name, interval, days_ago) in &[("A", 3, 5), ("B", 5, 5), ("C", 10, 5), ("D", 1, 9)] {
+            let last = now - Duration::days(*days_ago);
+            table
+                .add_entry(Entry::new(name.to_string(), *interval, last))
+                .unwrap();
+        }
+        table.suspend_entry("D".to_string()).unwrap();
+        let counts = Summary::from_table(&table).counts(now.date_naive());
+        assert_eq!(
+            Counts {
+                overdue: 1,
+                today: 1,
+                week: 2,
+                active: 3
+            },
+            counts
+        );
+        assert_eq!(
+            "1 overdue, 2 this week",
+            counts.render("{overdue} overdue, {week} this week")
+        );
+    }
+}
@@ -1,8 +1,49 @@
 use clap::ArgMatches;
 use log::{error, info};
 
-use crate::helpers::{get_date, get_interval, get_table_path, update_autocomplete_names};
-use crate::table::{Entry, Table};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::actions::{self, ImportFailed};
+use crate::agenda::Agenda;
+use crate::completions;
+use crate::config::Config;
+use crate::digest::{self, Digest};
+use crate::helpers::{
+    get_config_path, get_hooks, get_name, get_notified_path, get_shell_history_path,
+    get_status_path, get_table_path, get_view_config, today,
+};
+use crate::hooks;
+use crate::notify::{self, DesktopNotifier, FileNotifier, Notifier, Sent};
+use crate::parse::parse_duration;
+use crate::query::View;
+use crate::reminders::{self, Reminder};
+use crate::render::{Format, Layout};
+use crate::shell;
+use crate::stats::{PersonHistory, Stats};
+use crate::status::Summary;
+use crate::table::{ExistingEntry, Table};
+use crate::tui;
+
+/// Logs the error of an action and exits.
+fn fail(e: Box<dyn std::error::Error>) -> ! {
+    if e.is::<ExistingEntry>() {
+        error!(
+            "{} Please choose a different name or modify the existing entry.",
+            e
+        );
+        std::process::exit(exitcode::CANTCREAT);
+    }
+    error!("{}", e);
+    if e.is::<ImportFailed>() {
+        std::process::exit(exitcode::DATAERR);
+    }
+    if e.is::<std::io::Error>() {
+        std::process::exit(exitcode::IOERR);
+    }
+    std::process::exit(exitcode::USAGE);
+}
 
 pub fn just_talked_to(args: ArgMatches) {
     let table_path = get_table_path();
@@ -11,45 +52,19 @@ pub fn just_talked_to(args: ArgMatches) {
         eprintln!("Application error: {}", e);
         std::process::exit(exitcode::USAGE);
     }
-    let data = &mut data.unwrap();
     let c = args.subcommand_matches("just-talked-to").unwrap();
-    let name = c.value_of("name").unwrap();
-    let entry = data.entries.get_mut(name);
-    if entry.is_none() {
-        error!("Name {:?} is not in the list.", name);
-        std::process::exit(exitcode::USAGE);
+    if let Err(e) = actions::just_talked_to(&mut data.unwrap(), &table_path, c) {
+        fail(e);
     }
-    let entry = entry.unwrap();
-    entry.last_contact = get_date("now");
-    entry.update_remaining_time();
-    data.to_json(&table_path);
-    info!("Modified {:?}.", name);
 }
 
 pub fn add(args: ArgMatches) {
     let table_path = get_table_path();
-    let c = args.subcommand_matches("add").unwrap();
-    let name = c.value_of("name").unwrap();
-    let interval = get_interval(c.value_of("interval").unwrap());
-    let last_chat = get_date(c.value_of("last chat").unwrap());
     let mut data = Table::from_json(&table_path).unwrap_or_else(|_| Table::new());
-    if data
-        .add_entry(Entry::new(name.to_string(), interval, last_chat))
-        .is_err()
-    {
-        error!(
-            "Name {:?} is already used.  \
-            Please choose a different name or modify the \
-            existing entry.",
-            name
-        );
-        std::process::exit(exitcode::CANTCREAT);
-    }
-    if !c.is_present("no-autocomplete-update") {
-        update_autocomplete_names(&data).expect("Autocomplete update failed!");
+    let c = args.subcommand_matches("add").unwrap();
+    if let Err(e) = actions::add(&mut data, &table_path, c) {
+        fail(e);
     }
-    data.to_json(&table_path);
-    info!("Added {:?}.", name);
 }
 
 pub fn remove(args: ArgMatches) {
@@ -60,14 +75,9 @@ pub fn remove(args: ArgMatches) {
         std::process::exit(exitcode::USAGE);
     }
     let c = args.subcommand_matches("remove").unwrap();
-    let name = c.value_of("name").unwrap();
-    let mut data = data.unwrap();
-    if data.remove_entry(name.to_string()).is_err() {
-        error!("Name {:?} is not in the list.", name);
-        std::process::exit(exitcode::USAGE);
+    if let Err(e) = actions::remove(&mut data.unwrap(), &table_path, c) {
+        fail(e);
     }
-    data.to_json(&table_path);
-    info!("Removed {:?}.", name);
 }
 
 pub fn modify(args: ArgMatches) {
@@ -77,125 +87,492 @@ pub fn modify(args: ArgMatches) {
         eprintln!("Application error: {}", e);
         std::process::exit(exitcode::USAGE);
     }
-    let data = &mut data.unwrap();
     let c = args.subcommand_matches("modify").unwrap();
-    let name = c.value_of("name").unwrap();
-    let entry = data.entries.get_mut(name);
-    if entry.is_none() {
-        error!("Name {:?} is not in the list.", name);
-        std::process::exit(exitcode::USAGE);
-    }
-    let entry = entry.unwrap();
-    let raw_new_val = c.value_of("new value").unwrap();
-    match c.value_of("field").unwrap() {
-        "name" => {
-            let new_entry = Entry::new(raw_new_val.to_string(), entry.interval, entry.last_contact);
-            data.add_entry(new_entry).unwrap();
-            data.remove_entry(name.to_string()).unwrap();
-            if !c.is_present("no-autocomplete-update") {
-                update_autocomplete_names(&data).expect("Autocomplete update failed!");
-            }
-        }
-        "interval" => {
-            entry.interval = get_interval(raw_new_val);
-            entry.update_remaining_time();
-        }
-        "last" => {
-            entry.last_contact = get_date(raw_new_val);
-            entry.update_remaining_time();
-        }
-        _ => {
-            error!("Invalid field id. Use of 'name', 'interval', 'last'.");
+    if let Err(e) = actions::modify(&mut data.unwrap(), &table_path, c) {
+        fail(e);
+    }
+}
+
+/// The layout asked for with the options of a view subcommand.
+fn get_layout(c: &ArgMatches) -> Layout {
+    match Layout::from_args(c, &get_view_config()) {
+        Ok(layout) => layout,
+        Err(e) => {
+            eprintln!("{}", e);
             std::process::exit(exitcode::USAGE);
         }
     }
-    data.to_json(&table_path);
-    info!("Modified {:?}.", name);
 }
 
 // A lot of duplicated code here, this should be wrapped.
-pub fn view(_args: ArgMatches) {
+pub fn view(args: ArgMatches) {
     let table_path = get_table_path();
     let data = Table::from_json(&table_path);
     if let Err(e) = data {
         eprintln!("Application error: {}", e);
         std::process::exit(exitcode::USAGE);
     }
+    let c = args.subcommand_matches("view").unwrap();
+    let view = match View::from_args(c) {
+        Ok(view) => view,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(exitcode::USAGE);
+        }
+    };
+    let layout = get_layout(c);
     let mut data = data.unwrap();
     data.update_entries_par();
-    data.print_header();
-    data.print_all_by_remaining_time();
+    match view {
+        Some(view) => data.print_entries(&view.select(&data), &layout),
+        None => data.print_all_by_remaining_time(&layout),
+    }
 }
 
-pub fn view_active(_args: ArgMatches) {
+pub fn view_active(args: ArgMatches) {
     let table_path = get_table_path();
     let data = Table::from_json(&table_path);
     if let Err(e) = data {
         eprintln!("Application error: {}", e);
         std::process::exit(exitcode::USAGE);
     }
+    let layout = get_layout(args.subcommand_matches("view-active").unwrap());
     let mut data = data.unwrap();
     data.update_entries_par();
-    data.print_header();
-    data.print_active_by_remaining_time();
+    data.print_active_by_remaining_time(&layout);
 }
 
-pub fn view_inactive(_args: ArgMatches) {
+pub fn view_inactive(args: ArgMatches) {
     let table_path = get_table_path();
     let data = Table::from_json(&table_path);
     if let Err(e) = data {
         eprintln!("Application error: {}", e);
         std::process::exit(exitcode::USAGE);
     }
+    let layout = get_layout(args.subcommand_matches("view-inactive").unwrap());
     let mut data = data.unwrap();
     data.update_entries_par();
-    data.print_header();
-    data.print_inactive_by_remaining_time();
+    data.print_inactive_by_remaining_time(&layout);
 }
 
-pub fn resume(args: ArgMatches) {
+pub fn tui(_args: ArgMatches) {
     let table_path = get_table_path();
     let data = Table::from_json(&table_path);
     if let Err(e) = data {
         eprintln!("Application error: {}", e);
         std::process::exit(exitcode::USAGE);
     }
-    let c = args.subcommand_matches("resume").unwrap();
-    let name = c.value_of("name").unwrap();
+    if !std::io::stdout().is_terminal() {
+        error!("kit tui needs to run in a terminal.");
+        std::process::exit(exitcode::USAGE);
+    }
     let mut data = data.unwrap();
-    if data.resume_entry(name.to_string()).is_err() {
+    data.update_entries_par();
+    let mut app = tui::App::new(data, Some(table_path), get_hooks());
+    if let Err(e) = tui::run(&mut app) {
+        error!("Running the interface failed: {}", e);
+        std::process::exit(exitcode::IOERR);
+    }
+}
+
+pub fn edit(args: ArgMatches) {
+    let table_path = get_table_path();
+    let data = Table::from_json(&table_path);
+    if let Err(e) = data {
+        eprintln!("Application error: {}", e);
         std::process::exit(exitcode::USAGE);
     }
-    data.to_json(&table_path);
-    info!("Resumed {:?}.", name);
+    let c = args.subcommand_matches("edit").unwrap();
+    if let Err(e) = actions::edit(&mut data.unwrap(), &table_path, c) {
+        fail(e);
+    }
 }
 
-pub fn suspend(args: ArgMatches) {
+pub fn shell(_args: ArgMatches) {
+    let table_path = get_table_path();
+    let data = if table_path.exists() {
+        Table::from_json(&table_path)
+    } else {
+        Ok(Table::new())
+    };
+    if let Err(e) = data {
+        eprintln!("Application error: {}", e);
+        std::process::exit(exitcode::USAGE);
+    }
+    if !std::io::stdin().is_terminal() {
+        error!("kit shell needs to run in a terminal.");
+        std::process::exit(exitcode::USAGE);
+    }
+    let mut data = data.unwrap();
+    if let Err(e) = shell::run(&mut data, &table_path, &get_shell_history_path()) {
+        error!("Running the shell failed: {}", e);
+        std::process::exit(exitcode::IOERR);
+    }
+}
+
+/// Exit code of `kit check` when entries need attention. It isn't an error,
+/// so scripts can tell it apart from the codes of `exitcode`.
+const DUE: exitcode::ExitCode = 1;
+
+/// Lists the entries that need attention and exits with `1` if there are any.
+pub fn check(args: ArgMatches) {
     let table_path = get_table_path();
     let data = Table::from_json(&table_path);
     if let Err(e) = data {
         eprintln!("Application error: {}", e);
         std::process::exit(exitcode::USAGE);
     }
-    let c = args.subcommand_matches("suspend").unwrap();
-    let name = c.value_of("name").unwrap();
     let mut data = data.unwrap();
-    if data.suspend_entry(name.to_string()).is_err() {
+    let c = args.subcommand_matches("check").unwrap();
+    // overdue entries are always included, `--overdue` reports only those
+    let max_remaining = match c.value_of("due within").map(parse_duration) {
+        Some(Ok(days)) => days.max(-1),
+        Some(Err(e)) => {
+            error!("{}", e);
+            std::process::exit(exitcode::USAGE);
+        }
+        None => -1,
+    };
+    data.update_entries_par();
+    let due = data.due_entries(max_remaining);
+    if due.is_empty() {
+        std::process::exit(exitcode::OK);
+    }
+    if c.is_present("quiet") {
+        println!("{}", due.len());
+    } else {
+        for e in due {
+            println!("{}: {}", e.name, e.remaining_description());
+        }
+    }
+    std::process::exit(DUE);
+}
+
+/// Reads the cached summary, or rebuilds it if the table was written after it.
+fn get_summary() -> Summary {
+    let table_path = get_table_path();
+    let status_path = get_status_path();
+    let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    if modified(&status_path) >= modified(&table_path) {
+        if let Ok(summary) = Summary::from_json(&status_path) {
+            return summary;
+        }
+    }
+    let data = Table::from_json(&table_path);
+    if let Err(e) = data {
+        eprintln!("Application error: {}", e);
         std::process::exit(exitcode::USAGE);
     }
-    data.to_json(&table_path);
-    info!("Suspended {:?}.", name);
+    let summary = Summary::from_table(&data.unwrap());
+    summary.to_json(&status_path);
+    summary
 }
 
-pub fn update_autocompletion(_args: ArgMatches) {
+pub fn status(args: ArgMatches) {
+    let c = args.subcommand_matches("status").unwrap();
+    let prompt = c.is_present("prompt");
+    let counts = get_summary().counts(today());
+    if prompt && counts.overdue == 0 && counts.today == 0 {
+        return;
+    }
+    let default_format = if prompt {
+        "{overdue} overdue"
+    } else {
+        "{overdue} overdue, {today} due today, {week} due this week, {active} active"
+    };
+    // prompts capture the output, so they need `--color` to get colors
+    let line = counts.render(c.value_of("format").unwrap_or(default_format));
+    println!("{}", counts.colorize(line));
+}
+
+fn get_notifier(c: &ArgMatches) -> Box<dyn Notifier> {
+    match c.value_of("to file") {
+        Some(path) => Box::new(FileNotifier {
+            path: PathBuf::from(path),
+        }),
+        None => Box::new(DesktopNotifier),
+    }
+}
+
+/// Loads the table and sends notifications for newly due entries.
+fn notify_once(notifier: &mut dyn Notifier) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut data = Table::from_json(&get_table_path())?;
+    data.update_entries_par();
+    let sent_path = get_notified_path();
+    let mut sent = Sent::from_json(&sent_path).unwrap_or_default();
+    let notified = notify::notify_due(&data, &mut sent, notifier);
+    // also remember notifications that were sent before a failure
+    sent.to_json(&sent_path);
+    let notified = notified?;
+    let hooks = get_hooks();
+    for name in &notified {
+        hooks::run(&hooks, "entry.due", &data.entries[name], None);
+    }
+    Ok(notified.len())
+}
+
+pub fn notify(args: ArgMatches) {
+    let c = args.subcommand_matches("notify").unwrap();
+    match notify_once(get_notifier(c).as_mut()) {
+        Ok(count) => info!("Sent {} notification(s).", count),
+        Err(e) => {
+            eprintln!("Application error: {}", e);
+            std::process::exit(exitcode::UNAVAILABLE);
+        }
+    }
+}
+
+pub fn daemon(args: ArgMatches) {
+    let c = args.subcommand_matches("daemon").unwrap();
+    let minutes = match c.value_of("every").unwrap().parse::<u64>() {
+        Ok(minutes) if minutes > 0 => minutes,
+        _ => {
+            error!("Parsing the check interval failed. Please enter a positive integer.");
+            std::process::exit(exitcode::USAGE);
+        }
+    };
+    let mut notifier = get_notifier(c);
+    info!("Checking for due entries every {} minute(s).", minutes);
+    loop {
+        // keep running, the table or the notification service might be back next time
+        if let Err(e) = notify_once(notifier.as_mut()) {
+            error!("Sending notifications failed: {}", e);
+        }
+        std::thread::sleep(Duration::from_secs(minutes * 60));
+    }
+}
+
+pub fn agenda(args: ArgMatches) {
     let table_path = get_table_path();
     let data = Table::from_json(&table_path);
     if let Err(e) = data {
         eprintln!("Application error: {}", e);
         std::process::exit(exitcode::USAGE);
     }
-    if let Err(e) = update_autocomplete_names(&data.unwrap()) {
+    let c = args.subcommand_matches("agenda").unwrap();
+    let weeks = match c.value_of("weeks").unwrap().parse::<usize>() {
+        Ok(weeks) if weeks > 0 => weeks,
+        _ => {
+            eprintln!("The number of weeks has to be a positive number.");
+            std::process::exit(exitcode::USAGE);
+        }
+    };
+    let mut data = data.unwrap();
+    data.update_entries_par();
+    let agenda = Agenda::from_table(&data, today(), weeks);
+    match Format::from_args(c) {
+        Format::Text => print!("{}", agenda.to_text()),
+        Format::Json => println!("{}", agenda.to_json()),
+    }
+}
+
+pub fn stats(args: ArgMatches) {
+    let table_path = get_table_path();
+    let data = Table::from_json(&table_path);
+    if let Err(e) = data {
+        eprintln!("Application error: {}", e);
+        std::process::exit(exitcode::USAGE);
+    }
+    let c = args.subcommand_matches("stats").unwrap();
+    let weeks = match c.value_of("weeks").unwrap().parse::<usize>() {
+        Ok(weeks) if weeks > 0 => weeks,
+        _ => {
+            eprintln!("The number of weeks has to be a positive number.");
+            std::process::exit(exitcode::USAGE);
+        }
+    };
+    let mut data = data.unwrap();
+    data.update_entries_par();
+    let today = today();
+    let format = Format::from_args(c);
+    if let Some(raw) = c.value_of("name") {
+        let name = get_name(&data, raw);
+        let history = PersonHistory::from_entry(&data.entries[&name], today);
+        match format {
+            Format::Text => print!("{}", history.to_text()),
+            Format::Json => println!("{}", history.to_json()),
+        }
+        return;
+    }
+    let stats = Stats::from_table(&data, today, weeks);
+    match format {
+        Format::Text => print!("{}", stats.to_text()),
+        Format::Json => println!("{}", stats.to_json()),
+    }
+}
+
+pub fn digest(args: ArgMatches) {
+    let table_path = get_table_path();
+    let data = Table::from_json(&table_path);
+    if let Err(e) = data {
+        eprintln!("Application error: {}", e);
+        std::process::exit(exitcode::USAGE);
+    }
+    let mut data = data.unwrap();
+    let c = args.subcommand_matches("digest").unwrap();
+    data.update_entries_par();
+    let digest = Digest::from_table(&data, today());
+    if !c.is_present("email") {
+        print!("{}", digest.to_text());
+        return;
+    }
+    let config = match Config::from_json(&get_config_path()) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Reading the config failed: {}", e);
+            std::process::exit(exitcode::CONFIG);
+        }
+    };
+    let email = match config.email {
+        Some(email) => email,
+        None => {
+            error!("No email settings in the config, see `kit digest --help`.");
+            std::process::exit(exitcode::CONFIG);
+        }
+    };
+    let to = c.value_of("to").unwrap_or(&email.to);
+    let message = match digest.to_message(&email.from, to) {
+        Ok(message) => message,
+        Err(e) => {
+            error!("Building the email failed: {}", e);
+            std::process::exit(exitcode::CONFIG);
+        }
+    };
+    if c.is_present("dry run") {
+        print!("{}", String::from_utf8_lossy(&message.formatted()));
+        return;
+    }
+    if let Err(e) = digest::send(&message, &email) {
+        error!("Sending the digest failed: {}", e);
+        std::process::exit(exitcode::UNAVAILABLE);
+    }
+    info!("Sent digest to {:?}.", to);
+}
+
+pub fn install_reminders(args: ArgMatches) {
+    let c = args.subcommand_matches("install-reminders").unwrap();
+    let systemd = c.is_present("systemd");
+    let schedule = |arg: &str, systemd_default: &str, cron_default: &str| {
+        let default = if systemd {
+            systemd_default
+        } else {
+            cron_default
+        };
+        c.value_of(arg).unwrap_or(default).to_string()
+    };
+    let mut reminders = vec![Reminder {
+        name: "notify",
+        description: "notify about due contacts",
+        args: vec!["notify"],
+        schedule: schedule("notify schedule", "hourly", "0 * * * *"),
+    }];
+    if c.is_present("digest") {
+        reminders.push(Reminder {
+            name: "digest",
+            description: "send the digest of due contacts",
+            args: vec!["digest", "--email"],
+            schedule: schedule("digest schedule", "Mon 09:00", "0 9 * * 1"),
+        });
+    }
+    let dry_run = c.is_present("dry run");
+    let installed = if systemd {
+        reminders::install_systemd(&reminders, dry_run)
+    } else {
+        reminders::install_cron(&reminders, dry_run)
+    };
+    if let Err(e) = installed {
+        error!("Installing the reminders failed: {}", e);
+        std::process::exit(exitcode::CANTCREAT);
+    }
+}
+
+pub fn uninstall_reminders(args: ArgMatches) {
+    let c = args.subcommand_matches("uninstall-reminders").unwrap();
+    // without a choice, remove both kinds
+    let both = !c.is_present("systemd") && !c.is_present("cron");
+    if both || c.is_present("systemd") {
+        if let Err(e) = reminders::uninstall_systemd(reminders::REMINDER_NAMES) {
+            error!("Removing the systemd timers failed: {}", e);
+            std::process::exit(exitcode::IOERR);
+        }
+    }
+    if both || c.is_present("cron") {
+        if let Err(e) = reminders::uninstall_cron() {
+            error!("Removing the crontab entries failed: {}", e);
+            std::process::exit(exitcode::IOERR);
+        }
+    }
+}
+
+pub fn group(args: ArgMatches) {
+    let table_path = get_table_path();
+    let data = Table::from_json(&table_path);
+    if let Err(e) = data {
         eprintln!("Application error: {}", e);
         std::process::exit(exitcode::USAGE);
     }
+    let c = args.subcommand_matches("group").unwrap();
+    if let Err(e) = actions::group(&mut data.unwrap(), &table_path, c) {
+        fail(e);
+    }
+}
+
+pub fn resume(args: ArgMatches) {
+    let table_path = get_table_path();
+    let data = Table::from_json(&table_path);
+    if let Err(e) = data {
+        eprintln!("Application error: {}", e);
+        std::process::exit(exitcode::USAGE);
+    }
+    let c = args.subcommand_matches("resume").unwrap();
+    if let Err(e) = actions::resume(&mut data.unwrap(), &table_path, c) {
+        fail(e);
+    }
+}
+
+pub fn suspend(args: ArgMatches) {
+    let table_path = get_table_path();
+    let data = Table::from_json(&table_path);
+    if let Err(e) = data {
+        eprintln!("Application error: {}", e);
+        std::process::exit(exitcode::USAGE);
+    }
+    let c = args.subcommand_matches("suspend").unwrap();
+    if let Err(e) = actions::suspend(&mut data.unwrap(), &table_path, c) {
+        fail(e);
+    }
+}
+
+pub fn sync(args: ArgMatches) {
+    let table_path = get_table_path();
+    let data = Table::from_json(&table_path);
+    if let Err(e) = data {
+        eprintln!("Application error: {}", e);
+        std::process::exit(exitcode::USAGE);
+    }
+    let c = args.subcommand_matches("sync").unwrap();
+    if let Err(e) = actions::sync(&mut data.unwrap(), &table_path, c) {
+        fail(e);
+    }
+}
+
+pub fn completions(args: ArgMatches) {
+    let c = args.subcommand_matches("completions").unwrap();
+    let shell = c.value_of("shell").unwrap().parse::<clap::Shell>().unwrap();
+    if let Err(e) = completions::generate(shell, &mut std::io::stdout()) {
+        error!("Writing the completion script failed: {}", e);
+        std::process::exit(exitcode::IOERR);
+    }
+}
+
+/// Prints the candidates for a completion, one per line.
+/// Nothing is printed if the table can't be read.
+pub fn complete(args: ArgMatches) {
+    let c = args.subcommand_matches("__complete").unwrap();
+    let prefix = c.value_of("prefix").unwrap_or("");
+    if let Ok(data) = Table::from_json(&get_table_path()) {
+        for name in completions::names(&data, prefix) {
+            println!("{}", name);
+        }
+    }
 }
@@ -0,0 +1,149 @@
+//! Reader for the XML backups of the Android app "SMS Backup & Restore".
+
+use chrono::{DateTime, TimeZone, Utc};
+use std::error;
+
+/// A call or text message with a single phone number.
+#[derive(Debug, PartialEq)]
+pub struct PhoneRecord {
+    pub date: DateTime<Utc>,
+    pub number: String,
+    // `call` or `sms`
+    pub channel: &'static str,
+}
+
+/// Brings a phone number into E.164 format, e.g. `+4915112345678`.
+/// National numbers starting with a single `0` get `country_code` prepended,
+/// if one is given. Returns `None` for numbers without digits.
+pub fn normalize_phone(raw: &str, country_code: Option<&str>) -> Option<String> {
+    let digits = raw
+        .chars()
+        .filter(|c| c.is_ascii_digit())
+        .collect::<String>();
+    if digits.is_empty() {
+        None
+    } else if raw.trim_start().starts_with('+') {
+        Some(format!("+{}", digits))
+    } else if let Some(international) = digits.strip_prefix("00") {
+        Some(format!("+{}", international))
+    } else {
+        match (digits.strip_prefix('0'), country_code) {
+            (Some(national), Some(code)) => {
+                Some(format!("+{}{}", code.trim_start_matches('+'), national))
+            }
+            _ => Some(digits),
+        }
+    }
+}
+
+/// Backups store milliseconds since the epoch, some MMS only seconds.
+fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    let value = raw.parse::<i64>().ok()?;
+    if value < 100_000_000_000 {
+        Utc.timestamp_opt(value, 0).single()
+    } else {
+        Utc.timestamp_millis_opt(value).single()
+    }
+}
+
+/// Reads calls that lasted at least `min_duration` seconds as well as all
+/// text messages from a backup file. Calls and messages can be in the same
+/// file or in separate ones.
+pub fn parse_backup(
+    data: &str,
+    min_duration: u64,
+) -> Result<Vec<PhoneRecord>, Box<dyn error::Error>> {
+    let document = roxmltree::Document::parse(data)?;
+    let mut records = Vec::new();
+    for node in document.descendants().filter(|n| n.is_element()) {
+        let date = match node.attribute("date").and_then(parse_timestamp) {
+            Some(date) => date,
+            None => continue,
+        };
+        let (numbers, channel) = match node.tag_name().name() {
+            "call" => {
+                let duration = node
+                    .attribute("duration")
+                    .and_then(|d| d.parse::<u64>().ok())
+                    .unwrap_or(0);
+                if duration < min_duration {
+                    continue;
+                }
+                (node.attribute("number").into_iter().collect(), "call")
+            }
+            "sms" => (node.attribute("address").into_iter().collect(), "sms"),
+            // group messages list every participant separately
+            "mms" => (
+                node.descendants()
+                    .filter(|n| n.has_tag_name("addr"))
+                    .filter_map(|n| n.attribute("address"))
+                    .collect::<Vec<_>>(),
+                "sms",
+            ),
+            _ => continue,
+        };
+        for number in numbers {
+            records.push(PhoneRecord {
+                date,
+                number: number.to_string(),
+                channel,
+            });
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_phone() {
+        assert_eq!(
+            Some("+4915112345678".to_string()),
+            normalize_phone("+49 (151) 123-456 78", None)
+        );
+        assert_eq!(
+            Some("+4915112345678".to_string()),
+            normalize_phone("0049 151 12345678", None)
+        );
+        assert_eq!(
+            Some("+4915112345678".to_string()),
+            normalize_phone("0151 12345678", Some("+49"))
+        );
+        assert_eq!(
+            Some("015112345678".to_string()),
+            normalize_phone("0151 12345678", None)
+        );
+        assert_eq!(None, normalize_phone("Unknown", None));
+    }
+
+    #[test]
+    fn test_parse_backup() {
+        let data = r#"<?xml version='1.0' encoding='UTF-8' standalone='yes' ?>
+            <smses count="3">
+              <sms address="+4915112345678" date="1577986200000" type="1" body="Hi" />
+              <mms date="1577986260" msg_box="2">
+                <addrs>
+                  <addr address="+4915112345678" type="151" />
+                  <addr address="+4917000000000" type="137" />
+                </addrs>
+              </mms>
+              <call number="+4917000000000" duration="10" date="1577986300000" type="2" />
+              <call number="+4917000000000" duration="125" date="1577986400000" type="1" />
+            </smses>"#;
+        let records = parse_backup(data, 60).unwrap();
+        assert_eq!(
+            vec![
+                ("+4915112345678", "sms", 1577986200),
+                ("+4915112345678", "sms", 1577986260),
+                ("+4917000000000", "sms", 1577986260),
+                ("+4917000000000", "call", 1577986400),
+            ],
+            records
+                .iter()
+                .map(|r| (r.number.as_str(), r.channel, r.date.timestamp()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
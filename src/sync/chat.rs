@@ -0,0 +1,190 @@
+//! Parsers for exported chat histories.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use regex::Regex;
+use serde_json::Value;
+use std::error;
+
+/// A single chat message. Only the time and the sender are kept.
+#[derive(Debug, PartialEq)]
+pub struct ChatMessage {
+    pub date: DateTime<Utc>,
+    pub sender: String,
+}
+
+fn to_utc(date: NaiveDate, hour: u32, minute: u32, second: u32) -> Option<DateTime<Utc>> {
+    date.and_hms_opt(hour, minute, second)
+        .map(|d| Utc.from_utc_datetime(&d))
+}
+
+/// Parses the text export of WhatsApp, in both the Android format
+/// `31.12.20, 21:41 - Anna: Hi` and the iOS format `[31.12.20, 21:41:05] Anna: Hi`.
+/// Whether dates are written day or month first is guessed from the whole file.
+pub fn parse_whatsapp(data: &str) -> Vec<ChatMessage> {
+    let re = Regex::new(
+        r"^\[?(\d{1,2})[./-](\d{1,2})[./-](\d{2,4}),?\s+(\d{1,2}):(\d{2})(?::(\d{2}))?\s*([AaPp]\.?[Mm]\.?)?\]?\s*(?:-\s*)?([^:]+):\s",
+    )
+    .unwrap();
+    let lines = data
+        .lines()
+        .map(|l| l.trim_start_matches(['\u{200e}', '\u{feff}']))
+        .filter_map(|l| re.captures(l))
+        .collect::<Vec<_>>();
+    let number =
+        |c: &regex::Captures, i: usize| c.get(i).map_or(0, |m| m.as_str().parse::<u32>().unwrap());
+    let month_first = lines.iter().any(|c| number(c, 2) > 12);
+    lines
+        .iter()
+        .filter_map(|c| {
+            let (day, month) = if month_first {
+                (number(c, 2), number(c, 1))
+            } else {
+                (number(c, 1), number(c, 2))
+            };
+            let mut year = number(c, 3) as i32;
+            if year < 100 {
+                year += 2000;
+            }
+            let mut hour = number(c, 4);
+            match c.get(7).map(|m| m.as_str().to_lowercase().starts_with('p')) {
+                Some(true) if hour < 12 => hour += 12,
+                Some(false) if hour == 12 => hour = 0,
+                _ => (),
+            }
+            let date = NaiveDate::from_ymd_opt(year, month, day)?;
+            Some(ChatMessage {
+                date: to_utc(date, hour, number(c, 5), number(c, 6))?,
+                sender: c[8].trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Parses the plain text export of Signal, `[2020-12-31 21:41] Anna: Hi`,
+/// with optional seconds.
+pub fn parse_signal(data: &str) -> Vec<ChatMessage> {
+    let re = Regex::new(r"^\[(\d{4}-\d{2}-\d{2} \d{2}:\d{2}(?::\d{2})?)\]\s*([^:]+):\s").unwrap();
+    data.lines()
+        .filter_map(|l| re.captures(l))
+        .filter_map(|c| {
+            let date = NaiveDateTime::parse_from_str(&c[1], "%Y-%m-%d %H:%M:%S")
+                .or_else(|_| NaiveDateTime::parse_from_str(&c[1], "%Y-%m-%d %H:%M"))
+                .ok()?;
+            Some(ChatMessage {
+                date: Utc.from_utc_datetime(&date),
+                sender: c[2].trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn telegram_chat(chat: &Value, messages: &mut Vec<ChatMessage>) {
+    // in personal chats the own messages count as contact with the partner as well
+    let partner = match (chat["type"].as_str(), chat["name"].as_str()) {
+        (Some("personal_chat"), Some(name)) => Some(name),
+        _ => None,
+    };
+    for message in chat["messages"].as_array().into_iter().flatten() {
+        if message["type"].as_str() != Some("message") {
+            continue;
+        }
+        let date = match message["date_unixtime"].as_str() {
+            Some(unix) => unix
+                .parse()
+                .ok()
+                .and_then(|s| Utc.timestamp_opt(s, 0).single()),
+            None => message["date"]
+                .as_str()
+                .and_then(|d| NaiveDateTime::parse_from_str(d, "%Y-%m-%dT%H:%M:%S").ok())
+                .map(|d| Utc.from_utc_datetime(&d)),
+        };
+        let sender = partner.or_else(|| message["from"].as_str());
+        if let (Some(date), Some(sender)) = (date, sender) {
+            messages.push(ChatMessage {
+                date,
+                sender: sender.to_string(),
+            });
+        }
+    }
+}
+
+/// Parses the JSON export of Telegram Desktop, either of a single chat
+/// or of the whole account.
+pub fn parse_telegram(data: &str) -> Result<Vec<ChatMessage>, Box<dyn error::Error>> {
+    let export: Value = serde_json::from_str(data)?;
+    let mut messages = Vec::new();
+    match export["chats"]["list"].as_array() {
+        Some(chats) => chats.iter().for_each(|c| telegram_chat(c, &mut messages)),
+        None => telegram_chat(&export, &mut messages),
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(y: i32, mo: u32, d: u32, h: u32, mi: u32, s: u32, sender: &str) -> ChatMessage {
+        ChatMessage {
+            date: Utc.with_ymd_and_hms(y, mo, d, h, mi, s).unwrap(),
+            sender: sender.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_whatsapp_android() {
+        let data = "12/31/20, 9:41 PM - Messages are end-to-end encrypted.\n\
+            12/31/20, 9:41 PM - Anna Berg: Happy new year!\n\
+            a second line: of the message\n\
+            1/1/21, 12:05 AM - Me: You too";
+        assert_eq!(
+            vec![
+                message(2020, 12, 31, 21, 41, 0, "Anna Berg"),
+                message(2021, 1, 1, 0, 5, 0, "Me")
+            ],
+            parse_whatsapp(data)
+        );
+    }
+
+    #[test]
+    fn test_parse_whatsapp_ios() {
+        let data = "\u{200e}[02.01.20, 18:30:05] Anna: Hi";
+        assert_eq!(
+            vec![message(2020, 1, 2, 18, 30, 5, "Anna")],
+            parse_whatsapp(data)
+        );
+    }
+
+    #[test]
+    fn test_parse_signal() {
+        let data = "[2020-01-02 18:30] Anna: Hi\n[2020-01-02 18:31:10] Me: Hi!";
+        assert_eq!(
+            vec![
+                message(2020, 1, 2, 18, 30, 0, "Anna"),
+                message(2020, 1, 2, 18, 31, 10, "Me")
+            ],
+            parse_signal(data)
+        );
+    }
+
+    #[test]
+    fn test_parse_telegram() {
+        let data = r#"{"chats": {"list": [
+            {"name": "Anna", "type": "personal_chat", "messages": [
+                {"type": "message", "date": "2020-01-02T18:30:00", "from": "Me"},
+                {"type": "service", "date": "2020-01-02T18:31:00", "actor": "Anna"}
+            ]},
+            {"name": "Band", "type": "private_group", "messages": [
+                {"type": "message", "date": "2020-01-03T10:00:00",
+                 "date_unixtime": "1578045600", "from": "Ben"}
+            ]}
+        ]}}"#;
+        assert_eq!(
+            vec![
+                message(2020, 1, 2, 18, 30, 0, "Anna"),
+                message(2020, 1, 3, 10, 0, 0, "Ben")
+            ],
+            parse_telegram(data).unwrap()
+        );
+    }
+}
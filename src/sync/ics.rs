@@ -0,0 +1,203 @@
+//! A minimal reader for iCalendar (`.ics`) files.
+//! Only the parts needed to find out who was met when are parsed.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use log::warn;
+use std::error;
+use std::fmt;
+
+use super::Candidate;
+
+#[derive(Debug, Clone)]
+pub struct InvalidDate {
+    value: String,
+}
+
+impl fmt::Display for InvalidDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid calendar date {:?}", self.value)
+    }
+}
+
+impl error::Error for InvalidDate {
+    fn description(&self) -> &str {
+        "Invalid calendar date"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+#[derive(Debug, Default)]
+struct Event {
+    start: Option<Result<DateTime<Utc>, InvalidDate>>,
+    summary: String,
+    attendees: Vec<String>,
+}
+
+/// Joins folded content lines, see RFC 5545, section 3.1.
+fn unfold(data: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in data.lines() {
+        match (
+            line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+/// Parses `DATE` and `DATE-TIME` values. Times with a `Z` suffix are UTC, all others,
+/// whether floating or with a `TZID`, are read in the local time zone. The `TZID` isn't
+/// looked up, as that would need the time zone database or the `VTIMEZONE` definitions
+/// of the file. The calendars that are imported are usually the user's own, so their
+/// zone is the local one, and an event in another zone is at most a day off.
+fn parse_ics_date(value: &str) -> Result<DateTime<Utc>, InvalidDate> {
+    if let Some(raw) = value.strip_suffix('Z') {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(raw, "%Y%m%dT%H%M%S") {
+            return Ok(Utc.from_utc_datetime(&datetime));
+        }
+    }
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        // a time skipped by a daylight saving change is read as UTC
+        return Ok(Local
+            .from_local_datetime(&datetime)
+            .earliest()
+            .map(|d| d.with_timezone(&Utc))
+            .unwrap_or_else(|| Utc.from_utc_datetime(&datetime)));
+    }
+    match NaiveDate::parse_from_str(value, "%Y%m%d") {
+        Ok(date) => Ok(Utc.from_utc_datetime(&date.and_hms_opt(12, 12, 12).unwrap())),
+        Err(_) => Err(InvalidDate {
+            value: value.to_string(),
+        }),
+    }
+}
+
+/// Returns the common name of an attendee, or the address if no name is given.
+fn attendee_name(params: &str, value: &str) -> String {
+    params
+        .split(';')
+        .find_map(|p| p.strip_prefix("CN="))
+        .map(|cn| cn.trim_matches('"').to_string())
+        .unwrap_or_else(|| value.trim_start_matches("mailto:").to_string())
+}
+
+/// Reads all events that have a start date from the content of an `.ics` file.
+/// Events with a start date that can't be read are skipped with a warning.
+pub fn parse_ics(data: &str) -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+    let mut event: Option<Event> = None;
+    for line in unfold(data) {
+        let (key, value) = match line.split_once(':') {
+            Some(kv) => kv,
+            None => continue,
+        };
+        let (name, params) = key.split_once(';').unwrap_or((key, ""));
+        match (name.to_uppercase().as_str(), event.as_mut()) {
+            ("BEGIN", _) if value == "VEVENT" => event = Some(Event::default()),
+            ("END", Some(_)) if value == "VEVENT" => {
+                let e = event.take().unwrap();
+                match e.start {
+                    Some(Ok(start)) => {
+                        let mut texts = e.attendees;
+                        texts.push(e.summary.clone());
+                        candidates.push(Candidate {
+                            date: start,
+                            description: e.summary,
+                            texts,
+                        });
+                    }
+                    Some(Err(err)) => warn!("Skipped the event {:?}: {}", e.summary, err),
+                    None => (),
+                }
+            }
+            ("DTSTART", Some(e)) => e.start = Some(parse_ics_date(value)),
+            ("SUMMARY", Some(e)) => e.summary = unescape(value),
+            ("ATTENDEE", Some(e)) => e.attendees.push(attendee_name(params, value)),
+            _ => (),
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r\n\
+        BEGIN:VEVENT\r\n\
+        DTSTART;TZID=Europe/Berlin:20200102T183000\r\n\
+        SUMMARY:Dinner with Anna\\, Ben\r\n \u{20}and Carla\r\n\
+        ATTENDEE;CN=\"Dora D\";ROLE=REQ-PARTICIPANT:mailto:dora@example.com\r\n\
+        ATTENDEE:mailto:ed@example.com\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        DTSTART;VALUE=DATE:20200305\r\n\
+        SUMMARY:Birthday party\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        SUMMARY:No date\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+
+    #[test]
+    fn test_parse_ics_events() {
+        let candidates = parse_ics(CALENDAR);
+        assert_eq!(2, candidates.len());
+        // the time zone of the event is taken to be the local one
+        assert_eq!(
+            Local
+                .with_ymd_and_hms(2020, 1, 2, 18, 30, 0)
+                .unwrap()
+                .with_timezone(&Utc),
+            candidates[0].date
+        );
+        assert_eq!("Dinner with Anna, Ben and Carla", candidates[0].description);
+        assert_eq!(
+            vec![
+                "Dora D",
+                "ed@example.com",
+                "Dinner with Anna, Ben and Carla"
+            ],
+            candidates[0].texts
+        );
+        assert_eq!(
+            Utc.with_ymd_and_hms(2020, 3, 5, 12, 12, 12).unwrap(),
+            candidates[1].date
+        );
+    }
+
+    #[test]
+    fn test_parse_ics_utc() {
+        assert_eq!(
+            parse_ics_date("20200102T183000Z").unwrap(),
+            Utc.with_ymd_and_hms(2020, 1, 2, 18, 30, 0).unwrap()
+        );
+        assert!(parse_ics_date("20200102Z").is_err());
+    }
+
+    #[test]
+    fn test_parse_ics_invalid_date() {
+        let candidates = parse_ics(
+            "BEGIN:VEVENT\nDTSTART:2020-01-01\nSUMMARY:Broken\nEND:VEVENT\n\
+            BEGIN:VEVENT\nDTSTART:20200101T120000Z\nSUMMARY:Lunch\nEND:VEVENT\n",
+        );
+        assert_eq!(1, candidates.len());
+        assert_eq!("Lunch", candidates[0].description);
+    }
+}
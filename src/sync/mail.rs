@@ -0,0 +1,164 @@
+//! Reads the headers of emails stored in mbox files or Maildir directories.
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::HashSet;
+use std::error;
+use std::fs::{read, read_dir, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Date and all addresses involved in a single email.
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    pub date: DateTime<Utc>,
+    pub addresses: Vec<String>,
+}
+
+/// Parses the header block of an email.
+/// Returns `None` if the message has no readable date.
+pub fn parse_message(raw: &str) -> Option<Message> {
+    let address_re = Regex::new(r#"[^\s<>,;:"'()\[\]]+@[^\s<>,;:"'()\[\]]+"#).unwrap();
+    let mut headers: Vec<String> = Vec::new();
+    for line in raw.lines() {
+        if line.trim().is_empty() {
+            break;
+        }
+        match (
+            line.starts_with(' ') || line.starts_with('\t'),
+            headers.last_mut(),
+        ) {
+            (true, Some(last)) => last.push_str(line),
+            _ => headers.push(line.to_string()),
+        }
+    }
+    let mut date = None;
+    let mut addresses = Vec::new();
+    for header in headers {
+        let (key, value) = match header.split_once(':') {
+            Some(kv) => kv,
+            None => continue,
+        };
+        match key.trim().to_lowercase().as_str() {
+            "date" => {
+                // drop trailing comments like `(UTC)`
+                let value = value.split('(').next().unwrap().trim();
+                date = DateTime::parse_from_rfc2822(value)
+                    .ok()
+                    .map(|d| d.with_timezone(&Utc));
+            }
+            "from" | "to" | "cc" | "bcc" => addresses.extend(
+                address_re
+                    .find_iter(value)
+                    .map(|m| m.as_str().to_lowercase()),
+            ),
+            _ => (),
+        }
+    }
+    date.map(|date| Message { date, addresses })
+}
+
+/// Reads the messages of an mbox file, starting at byte `offset`.
+/// Returns the messages and the offset up to which the file was read.
+pub fn read_mbox(path: &Path, offset: u64) -> Result<(Vec<Message>, u64), Box<dyn error::Error>> {
+    let data = read(path)?;
+    let len = data.len() as u64;
+    // a file smaller than what we read before was rewritten, start over
+    let start = if offset > len { 0 } else { offset as usize };
+    let content = String::from_utf8_lossy(&data[start..]);
+    let mut messages = Vec::new();
+    let mut current = String::new();
+    for line in content.lines() {
+        if line.starts_with("From ") {
+            messages.extend(parse_message(&current));
+            current.clear();
+        } else {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    messages.extend(parse_message(&current));
+    Ok((messages, len))
+}
+
+/// Reads all messages of a Maildir whose unique names are not in `seen`,
+/// and adds their names to it.
+pub fn read_maildir(
+    path: &Path,
+    seen: &mut HashSet<String>,
+) -> Result<Vec<Message>, Box<dyn error::Error>> {
+    let mut messages = Vec::new();
+    for sub in &["cur", "new"] {
+        let dir = path.join(sub);
+        if !dir.is_dir() {
+            continue;
+        }
+        for file in read_dir(dir)? {
+            let file = file?;
+            // flags are appended after the colon and change when a message is read
+            let file_name = file.file_name().to_string_lossy().to_string();
+            let unique = file_name.split(':').next().unwrap().to_string();
+            if seen.contains(&unique) {
+                continue;
+            }
+            let mut header = String::new();
+            for line in BufReader::new(File::open(file.path())?).split(b'\n') {
+                let line = String::from_utf8_lossy(&line?).to_string();
+                if line.trim().is_empty() {
+                    break;
+                }
+                header.push_str(&line);
+                header.push('\n');
+            }
+            messages.extend(parse_message(&header));
+            seen.insert(unique);
+        }
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const MAIL: &str = "From: \"Anna Berg\" <Anna@Example.com>\n\
+        To: me@example.com,\n \tben@example.com\n\
+        Date: Thu, 2 Jan 2020 18:30:00 +0100 (CET)\n\
+        Subject: Hi\n\
+        \n\
+        From: not a header\n";
+
+    #[test]
+    fn test_parse_message() {
+        assert_eq!(
+            Some(Message {
+                date: Utc.with_ymd_and_hms(2020, 1, 2, 17, 30, 0).unwrap(),
+                addresses: vec![
+                    "anna@example.com".to_string(),
+                    "me@example.com".to_string(),
+                    "ben@example.com".to_string()
+                ],
+            }),
+            parse_message(MAIL)
+        );
+    }
+
+    #[test]
+    fn test_parse_message_without_date() {
+        assert!(parse_message("From: anna@example.com\n\nHi").is_none());
+    }
+
+    #[test]
+    fn test_read_mbox_incremental() {
+        let path = std::env::temp_dir().join(format!("kit-test-read-mbox-{}", std::process::id()));
+        let first = format!("From anna@example.com Thu Jan  2 18:30:00 2020\n{}", MAIL);
+        std::fs::write(&path, &first).unwrap();
+        let (messages, offset) = read_mbox(&path, 0).unwrap();
+        assert_eq!(1, messages.len());
+        assert_eq!(first.len() as u64, offset);
+        let (messages, _) = read_mbox(&path, offset).unwrap();
+        assert!(messages.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
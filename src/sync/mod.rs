@@ -0,0 +1,453 @@
+//! Importers that turn external records of meetings and conversations
+//! into interactions of the matching entries.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
+use std::fs::{read_to_string, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::helpers::confirm;
+use crate::table::{Interaction, Table};
+
+pub mod calls;
+pub mod chat;
+pub mod ics;
+pub mod mail;
+
+#[derive(Debug, Clone)]
+pub struct UnknownChatFormat {
+    format: String,
+}
+
+impl fmt::Display for UnknownChatFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Unknown chat format {:?}, expected one of whatsapp, telegram and signal",
+            self.format
+        )
+    }
+}
+
+impl error::Error for UnknownChatFormat {
+    fn description(&self) -> &str {
+        "Unknown chat format"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+/// Newly recorded interactions together with the names of their entries.
+pub type Recorded = Vec<(String, Interaction)>;
+
+/// Remembers which parts of a source have already been imported,
+/// so that repeated imports only read new data.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncState {
+    // Bytes already read, by mbox path
+    #[serde(default)]
+    pub mbox_offsets: HashMap<String, u64>,
+    // Unique names of messages already read, by Maildir path
+    #[serde(default)]
+    pub maildir_seen: HashMap<String, HashSet<String>>,
+}
+
+impl SyncState {
+    pub fn from_json(path: &Path) -> Result<Self, Box<dyn error::Error>> {
+        let json_file_str = read_to_string(path)?;
+        let data = serde_json::from_str(&json_file_str)?;
+        Ok(data)
+    }
+
+    pub fn to_json(&self, outpath: &Path) {
+        let mut file = BufWriter::new(File::create(outpath).expect("Error when creating outfile."));
+        serde_json::to_writer(&mut file, self).expect("Error writing to outfile.");
+    }
+}
+
+/// How confidently a piece of text refers to an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    // Only a part of the name or alias was found, e.g. the first name
+    Fuzzy,
+    // The whole name or alias was found
+    Exact,
+}
+
+/// Something that happened at a certain time and might involve
+/// one or more people from the table.
+#[derive(Debug)]
+pub struct Candidate {
+    pub date: DateTime<Utc>,
+    // Human readable description, shown when asking for confirmation
+    pub description: String,
+    // Texts that are matched against names and aliases
+    pub texts: Vec<String>,
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+fn contains_sequence(haystack: &[String], needle: &[String]) -> bool {
+    !needle.is_empty() && haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+/// Finds the entries that are referred to in `texts` by name or alias.
+/// Returns the names of the matched entries, sorted by name.
+pub fn find_entries<S: AsRef<str>>(table: &Table, texts: &[S]) -> Vec<(String, Confidence)> {
+    let text_words = texts.iter().map(|t| words(t.as_ref())).collect::<Vec<_>>();
+    let mut matches = Vec::new();
+    for entry in table.entries.values() {
+        let mut best = None;
+        for label in std::iter::once(&entry.name).chain(entry.aliases.iter()) {
+            let label_words = words(label);
+            for text in &text_words {
+                let confidence = if contains_sequence(text, &label_words) {
+                    Some(Confidence::Exact)
+                } else if label_words
+                    .iter()
+                    .any(|w| w.chars().count() >= 3 && text.contains(w))
+                {
+                    Some(Confidence::Fuzzy)
+                } else {
+                    None
+                };
+                best = best.max(confidence);
+            }
+        }
+        if let Some(confidence) = best {
+            matches.push((entry.name.clone(), confidence));
+        }
+    }
+    matches.sort();
+    matches
+}
+
+/// Decides whether an uncertain match between `description` and the entry `name` is used.
+/// Asks the user if `interactive` is set, otherwise skips it.
+fn accept_fuzzy(description: &str, name: &str, question: &str, interactive: bool) -> bool {
+    if !interactive {
+        info!(
+            "Skipped possible match {:?} for {:?}, use --interactive to confirm.",
+            description, name
+        );
+        false
+    } else {
+        confirm(question)
+    }
+}
+
+/// Records every candidate in the past as an interaction with the entries it matches.
+/// Fuzzy matches are only recorded if `interactive` is set and the user confirms them.
+/// Returns the newly recorded interactions with the names of their entries.
+pub fn record_candidates(
+    table: &mut Table,
+    candidates: &[Candidate],
+    channel: &str,
+    interactive: bool,
+) -> Recorded {
+    let now = Utc::now();
+    let mut recorded = Vec::new();
+    for candidate in candidates.iter().filter(|c| c.date <= now) {
+        for (name, confidence) in find_entries(table, &candidate.texts) {
+            if confidence == Confidence::Fuzzy {
+                let question = format!(
+                    "Record {:?} on {} as contact with {:?}?",
+                    candidate.description,
+                    candidate.date.date_naive(),
+                    name
+                );
+                if !accept_fuzzy(&candidate.description, &name, &question, interactive) {
+                    continue;
+                }
+            }
+            let interaction = Interaction {
+                date: candidate.date,
+                channel: channel.to_string(),
+            };
+            let entry = table.entries.get_mut(&name).unwrap();
+            if entry.record_interaction(interaction.clone()) {
+                info!(
+                    "Recorded contact with {:?} on {}.",
+                    name,
+                    candidate.date.date_naive()
+                );
+                recorded.push((name, interaction));
+            }
+        }
+    }
+    recorded
+}
+
+fn record_grouped<I>(table: &mut Table, dated_names: I, channel: &str, per_day: bool) -> Recorded
+where
+    I: IntoIterator<Item = (String, DateTime<Utc>)>,
+{
+    let now = Utc::now();
+    let mut latest: HashMap<(String, Option<NaiveDate>), DateTime<Utc>> = HashMap::new();
+    for (name, date) in dated_names.into_iter().filter(|(_, d)| *d <= now) {
+        let day = if per_day {
+            Some(date.date_naive())
+        } else {
+            None
+        };
+        let current = latest.entry((name, day)).or_insert(date);
+        *current = date.max(*current);
+    }
+    let mut latest = latest
+        .into_iter()
+        .map(|((name, _), date)| (date, name))
+        .collect::<Vec<_>>();
+    latest.sort();
+    let mut recorded = Vec::new();
+    for (date, name) in latest {
+        let interaction = Interaction {
+            date,
+            channel: channel.to_string(),
+        };
+        if let Some(entry) = table.entries.get_mut(&name) {
+            if entry.record_interaction(interaction.clone()) {
+                info!("Recorded contact with {:?} on {}.", name, date.date_naive());
+                recorded.push((name, interaction));
+            }
+        }
+    }
+    recorded
+}
+
+/// Records a single interaction per entry: the most recent of the given dates.
+/// Returns the newly recorded interactions with the names of their entries.
+pub fn record_latest<I>(table: &mut Table, dated_names: I, channel: &str) -> Recorded
+where
+    I: IntoIterator<Item = (String, DateTime<Utc>)>,
+{
+    record_grouped(table, dated_names, channel, false)
+}
+
+/// Records a single interaction per entry and day: the last of the given dates on that day.
+/// Returns the newly recorded interactions with the names of their entries.
+pub fn record_daily<I>(table: &mut Table, dated_names: I, channel: &str) -> Recorded
+where
+    I: IntoIterator<Item = (String, DateTime<Utc>)>,
+{
+    record_grouped(table, dated_names, channel, true)
+}
+
+/// Imports the events of an iCalendar file. See `record_candidates`.
+pub fn import_ics(
+    table: &mut Table,
+    path: &Path,
+    interactive: bool,
+) -> Result<Recorded, Box<dyn error::Error>> {
+    let candidates = ics::parse_ics(&read_to_string(path)?);
+    Ok(record_candidates(
+        table,
+        &candidates,
+        "calendar",
+        interactive,
+    ))
+}
+
+/// Imports the latest email exchange with every entry that has a matching address.
+/// `path` is either an mbox file or a Maildir directory.
+pub fn import_mail(
+    table: &mut Table,
+    path: &Path,
+    state: &mut SyncState,
+) -> Result<Recorded, Box<dyn error::Error>> {
+    let key = path.canonicalize()?.to_string_lossy().to_string();
+    let messages = if path.is_dir() {
+        mail::read_maildir(path, state.maildir_seen.entry(key).or_default())?
+    } else {
+        let offset = state.mbox_offsets.get(&key).copied().unwrap_or(0);
+        let (messages, offset) = mail::read_mbox(path, offset)?;
+        state.mbox_offsets.insert(key, offset);
+        messages
+    };
+    let mut by_address: HashMap<String, String> = HashMap::new();
+    for entry in table.entries.values() {
+        for email in &entry.emails {
+            by_address.insert(email.to_lowercase(), entry.name.clone());
+        }
+    }
+    let dated_names = messages
+        .iter()
+        .flat_map(|m| {
+            m.addresses
+                .iter()
+                .filter_map(|a| by_address.get(a))
+                .map(move |name| (name.clone(), m.date))
+        })
+        .collect::<Vec<_>>();
+    Ok(record_latest(table, dated_names, "email"))
+}
+
+/// Imports an exported chat history in the given `format`, which is one of
+/// `whatsapp`, `telegram` and `signal`. One interaction is recorded per entry and day.
+/// Senders are matched to entries by name, unless all messages are attributed to `name`.
+pub fn import_chat(
+    table: &mut Table,
+    path: &Path,
+    format: &str,
+    name: Option<&str>,
+    interactive: bool,
+) -> Result<Recorded, Box<dyn error::Error>> {
+    let data = read_to_string(path)?;
+    let messages = match format {
+        "whatsapp" => chat::parse_whatsapp(&data),
+        "signal" => chat::parse_signal(&data),
+        "telegram" => chat::parse_telegram(&data)?,
+        other => {
+            return Err(Box::new(UnknownChatFormat {
+                format: other.to_string(),
+            }))
+        }
+    };
+    let mut senders: HashMap<&str, Vec<String>> = HashMap::new();
+    for message in &messages {
+        let sender = message.sender.as_str();
+        if senders.contains_key(sender) {
+            continue;
+        }
+        let names = match name {
+            Some(name) => vec![name.to_string()],
+            None => find_entries(table, &[sender])
+                .into_iter()
+                .filter(|(name, confidence)| {
+                    let question = format!("Are the messages of {:?} from {:?}?", sender, name);
+                    *confidence == Confidence::Exact
+                        || accept_fuzzy(sender, name, &question, interactive)
+                })
+                .map(|(name, _)| name)
+                .collect(),
+        };
+        senders.insert(sender, names);
+    }
+    let dated_names = messages
+        .iter()
+        .flat_map(|m| {
+            senders[m.sender.as_str()]
+                .iter()
+                .map(move |name| (name.clone(), m.date))
+        })
+        .collect::<Vec<_>>();
+    Ok(record_daily(table, dated_names, format))
+}
+
+/// Imports calls and text messages from an "SMS Backup & Restore" XML file.
+/// Numbers are matched to the phone numbers of the entries after normalizing both,
+/// with `country_code` used for national numbers. One interaction is recorded
+/// per entry, day and channel.
+pub fn import_calls(
+    table: &mut Table,
+    path: &Path,
+    min_duration: u64,
+    country_code: Option<&str>,
+) -> Result<Recorded, Box<dyn error::Error>> {
+    let records = calls::parse_backup(&read_to_string(path)?, min_duration)?;
+    let mut by_number: HashMap<String, String> = HashMap::new();
+    for entry in table.entries.values() {
+        for phone in &entry.phones {
+            if let Some(number) = calls::normalize_phone(phone, country_code) {
+                by_number.insert(number, entry.name.clone());
+            }
+        }
+    }
+    let mut recorded = Vec::new();
+    for channel in &["call", "sms"] {
+        let dated_names = records
+            .iter()
+            .filter(|r| r.channel == *channel)
+            .filter_map(|r| {
+                calls::normalize_phone(&r.number, country_code)
+                    .and_then(|n| by_number.get(&n))
+                    .map(|name| (name.clone(), r.date))
+            })
+            .collect::<Vec<_>>();
+        recorded.extend(record_daily(table, dated_names, channel));
+    }
+    Ok(recorded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Entry;
+    use chrono::TimeZone;
+
+    fn table() -> Table {
+        let mut table = Table::new();
+        let mut anna = Entry::new("Anna Berg".to_string(), 30, Utc::now());
+        anna.aliases.push("Annie".to_string());
+        table.add_entry(anna).unwrap();
+        table
+            .add_entry(Entry::new("Ben".to_string(), 30, Utc::now()))
+            .unwrap();
+        table
+    }
+
+    #[test]
+    fn test_find_entries_exact_and_alias() {
+        let table = table();
+        assert_eq!(
+            vec![("Anna Berg".to_string(), Confidence::Exact)],
+            find_entries(&table, &["Lunch with anna berg"])
+        );
+        assert_eq!(
+            vec![("Anna Berg".to_string(), Confidence::Exact)],
+            find_entries(&table, &["Coffee w/ Annie"])
+        );
+    }
+
+    #[test]
+    fn test_find_entries_fuzzy() {
+        let table = table();
+        assert_eq!(
+            vec![
+                ("Anna Berg".to_string(), Confidence::Fuzzy),
+                ("Ben".to_string(), Confidence::Exact)
+            ],
+            find_entries(&table, &["Dinner: Anna & Ben"])
+        );
+        assert!(find_entries(&table, &["Bench press"]).is_empty());
+    }
+
+    #[test]
+    fn test_record_latest_only_once_per_entry() {
+        let mut table = table();
+        let old = Utc.with_ymd_and_hms(2020, 1, 2, 12, 0, 0).unwrap();
+        let new = Utc.with_ymd_and_hms(2020, 2, 2, 12, 0, 0).unwrap();
+        let recorded = record_latest(
+            &mut table,
+            vec![("Ben".to_string(), new), ("Ben".to_string(), old)],
+            "email",
+        );
+        assert_eq!(1, recorded.len());
+        let ben = table.entries.get("Ben").unwrap();
+        assert_eq!(
+            vec![new],
+            ben.interactions.iter().map(|i| i.date).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_import_chat_unknown_format() {
+        let path = std::env::temp_dir().join(format!("kit-test-chat-{}.txt", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let e = import_chat(&mut Table::new(), &path, "icq", None, false).unwrap_err();
+        assert!(e.is::<UnknownChatFormat>());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
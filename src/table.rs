@@ -1,17 +1,21 @@
 //! The `table` mod contains structs that hold the actual
 //! data written, stored and read by the application.
 
-use chrono::{DateTime, Utc};
-use colored::Colorize;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::error;
 use std::fmt;
-use std::fs::{read_to_string, File};
+use std::fs::{read_to_string, rename, File};
 use std::io::BufWriter;
 use std::path::Path;
 
+use crate::helpers::today;
+use crate::render::Layout;
+use crate::status::Summary;
+use crate::STATUS_LOC;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SuspendedEntry {
     name: String,
@@ -28,7 +32,7 @@ impl error::Error for SuspendedEntry {
         "Entry is suspended."
     }
 
-    fn cause(&self) -> Option<&(dyn error::Error)> {
+    fn cause(&self) -> Option<&dyn error::Error> {
         // Generic error, underlying cause isn't tracked.
         None
     }
@@ -50,7 +54,7 @@ impl error::Error for UnsuspendedEntry {
         "Entry is not suspended."
     }
 
-    fn cause(&self) -> Option<&(dyn error::Error)> {
+    fn cause(&self) -> Option<&dyn error::Error> {
         // Generic error, underlying cause isn't tracked.
         None
     }
@@ -58,12 +62,12 @@ impl error::Error for UnsuspendedEntry {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExistingEntry {
-    name: String,
+    pub name: String,
 }
 
 impl fmt::Display for ExistingEntry {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Name {} already used", self.name)
+        write!(f, "Name {:?} is already used.", self.name)
     }
 }
 
@@ -72,7 +76,7 @@ impl error::Error for ExistingEntry {
         "Name already used"
     }
 
-    fn cause(&self) -> Option<&(dyn error::Error)> {
+    fn cause(&self) -> Option<&dyn error::Error> {
         // Generic error, underlying cause isn't tracked.
         None
     }
@@ -94,17 +98,50 @@ impl error::Error for MissingEntry {
         "Name not in list"
     }
 
-    fn cause(&self) -> Option<&(dyn error::Error)> {
+    fn cause(&self) -> Option<&dyn error::Error> {
         // Generic error, underlying cause isn't tracked.
         None
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissingGroup {
+    pub name: String,
+}
+
+impl fmt::Display for MissingGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Group {:?} doesn't exist.", self.name)
+    }
+}
+
+impl error::Error for MissingGroup {
+    fn description(&self) -> &str {
+        "Group doesn't exist"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+/// People that are usually seen together, like a band or a household.
+/// A contact with the group counts as a contact with every member.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Group {
+    pub name: String,
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Table {
     pub entries: HashMap<String, Entry>,
     #[serde(default)]
     pub suspended_entries: HashSet<String>,
+    // groups and entries share one namespace
+    #[serde(default)]
+    pub groups: HashMap<String, Group>,
     // thresholds for highlighting
     t1: i64,
     t2: i64,
@@ -116,6 +153,7 @@ impl Table {
         Table {
             entries: HashMap::new(),
             suspended_entries: HashSet::new(),
+            groups: HashMap::new(),
             t1: 0,
             t2: 3,
             t3: 10,
@@ -128,14 +166,22 @@ impl Table {
         Ok(data)
     }
 
+    /// Writes the table and updates the summary next to it.
+    /// The table is written to a temporary file first and then moved over
+    /// the old one, so it is never left half written.
     pub fn to_json(&self, outpath: &Path) {
-        let mut file =
-            BufWriter::new(File::create(&outpath).expect("Error when creating outfile."));
-        serde_json::to_writer(&mut file, self).expect("Error writing to outfile.");
+        let tmp_path = outpath.with_extension("json.tmp");
+        {
+            let mut file =
+                BufWriter::new(File::create(&tmp_path).expect("Error when creating outfile."));
+            serde_json::to_writer(&mut file, self).expect("Error writing to outfile.");
+        }
+        rename(&tmp_path, outpath).expect("Error replacing outfile.");
+        Summary::from_table(self).to_json(&outpath.with_file_name(STATUS_LOC));
     }
 
     pub fn add_entry(&mut self, entry: Entry) -> Result<(), ExistingEntry> {
-        if self.entries.contains_key(&entry.name) {
+        if self.entries.contains_key(&entry.name) || self.groups.contains_key(&entry.name) {
             Err(ExistingEntry { name: entry.name })
         } else {
             self.entries.insert(entry.name.clone(), entry);
@@ -147,10 +193,136 @@ impl Table {
         if self.entries.remove(&name).is_none() {
             Err(MissingEntry { name })
         } else {
+            self.suspended_entries.remove(&name);
+            for group in self.groups.values_mut() {
+                group.members.retain(|m| *m != name);
+            }
             Ok(())
         }
     }
 
+    /// Moves an entry to a new name, keeping its history and suspension state.
+    pub fn rename_entry(
+        &mut self,
+        name: String,
+        new_name: String,
+    ) -> Result<(), Box<dyn error::Error>> {
+        if self.entries.contains_key(&new_name) || self.groups.contains_key(&new_name) {
+            return Err(Box::new(ExistingEntry { name: new_name }));
+        }
+        let mut entry = match self.entries.remove(&name) {
+            Some(entry) => entry,
+            None => return Err(Box::new(MissingEntry { name })),
+        };
+        entry.name = new_name.clone();
+        if self.suspended_entries.remove(&name) {
+            self.suspended_entries.insert(new_name.clone());
+        }
+        for group in self.groups.values_mut() {
+            for member in group.members.iter_mut().filter(|m| **m == name) {
+                *member = new_name.clone();
+            }
+        }
+        self.entries.insert(new_name, entry);
+        Ok(())
+    }
+
+    /// Adds a group of existing entries.
+    pub fn add_group(&mut self, group: Group) -> Result<(), Box<dyn error::Error>> {
+        if self.entries.contains_key(&group.name) || self.groups.contains_key(&group.name) {
+            return Err(Box::new(ExistingEntry { name: group.name }));
+        }
+        if let Some(missing) = group
+            .members
+            .iter()
+            .find(|m| !self.entries.contains_key(*m))
+        {
+            return Err(Box::new(MissingEntry {
+                name: missing.to_string(),
+            }));
+        }
+        let mut members: Vec<String> = Vec::new();
+        for member in group.members {
+            if !members.contains(&member) {
+                members.push(member);
+            }
+        }
+        self.groups.insert(
+            group.name.clone(),
+            Group {
+                name: group.name,
+                members,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn remove_group(&mut self, name: String) -> Result<Group, MissingGroup> {
+        match self.groups.remove(&name) {
+            Some(group) => Ok(group),
+            None => Err(MissingGroup { name }),
+        }
+    }
+
+    /// Adds entries to a group, skipping those that are members already.
+    pub fn add_members(
+        &mut self,
+        group: &str,
+        members: &[String],
+    ) -> Result<(), Box<dyn error::Error>> {
+        if let Some(missing) = members.iter().find(|m| !self.entries.contains_key(*m)) {
+            return Err(Box::new(MissingEntry {
+                name: missing.to_string(),
+            }));
+        }
+        let group = match self.groups.get_mut(group) {
+            Some(group) => group,
+            None => {
+                return Err(Box::new(MissingGroup {
+                    name: group.to_string(),
+                }))
+            }
+        };
+        for member in members {
+            if !group.members.contains(member) {
+                group.members.push(member.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove_members(
+        &mut self,
+        group: &str,
+        members: &[String],
+    ) -> Result<(), Box<dyn error::Error>> {
+        let group = match self.groups.get_mut(group) {
+            Some(group) => group,
+            None => {
+                return Err(Box::new(MissingGroup {
+                    name: group.to_string(),
+                }))
+            }
+        };
+        if let Some(missing) = members.iter().find(|m| !group.members.contains(*m)) {
+            return Err(Box::new(MissingEntry {
+                name: missing.to_string(),
+            }));
+        }
+        group.members.retain(|m| !members.contains(m));
+        Ok(())
+    }
+
+    /// The active member that is due first, it stands for the whole group.
+    pub fn most_urgent_member(&self, group: &Group) -> Option<&Entry> {
+        group
+            .members
+            .iter()
+            .filter(|m| !self.suspended_entries.contains(*m))
+            .filter_map(|m| self.entries.get(m))
+            .min_by_key(|e| e.remaining_time)
+    }
+
     pub fn resume_entry(&mut self, name: String) -> Result<(), Box<dyn error::Error>> {
         if !self.entries.contains_key(&name) {
             Err(Box::new(MissingEntry { name }))
@@ -179,44 +351,84 @@ impl Table {
             .for_each(|(_k, v)| v.update_remaining_time());
     }
 
-    pub fn print_header(&self) {
-        println!(
-            "{0: <15}  {1: <15}  {2: <15}  {3: <15}",
-            "Name".white().on_black().bold(),
-            "Remaining".white().on_black().bold(),
-            "Last".white().on_black().bold(),
-            "Interval".white().on_black().bold()
-        );
+    /// Active entries with at most `max_remaining` days left, most urgent first.
+    pub fn due_entries(&self, max_remaining: i64) -> Vec<&Entry> {
+        let mut due = self
+            .entries
+            .values()
+            .filter(|e| !self.suspended_entries.contains(&e.name))
+            .filter(|e| e.remaining_time <= max_remaining)
+            .collect::<Vec<&Entry>>();
+        due.sort_by_key(|e| e.remaining_time);
+        due
+    }
+
+    /// Classifies an entry by the highlighting thresholds of the table.
+    pub fn urgency(&self, entry: &Entry) -> Urgency {
+        if self.suspended_entries.contains(&entry.name) {
+            Urgency::Suspended
+        } else if entry.remaining_time < self.t1 {
+            Urgency::Overdue
+        } else if entry.remaining_time < self.t2 {
+            Urgency::Soon
+        } else if entry.remaining_time > self.t3 {
+            Urgency::Relaxed
+        } else {
+            Urgency::Normal
+        }
+    }
+
+    pub fn print_all_by_remaining_time(&self, layout: &Layout) {
+        let mut rows = self.active_by_remaining_time();
+        rows.extend(self.inactive_by_remaining_time());
+        layout.print(self, &rows);
+    }
+
+    pub fn print_active_by_remaining_time(&self, layout: &Layout) {
+        layout.print(self, &self.active_by_remaining_time());
     }
 
-    pub fn print_all_by_remaining_time(&self) {
-        self.print_active_by_remaining_time();
-        self.print_inactive_by_remaining_time();
+    pub fn print_inactive_by_remaining_time(&self, layout: &Layout) {
+        layout.print(self, &self.inactive_by_remaining_time());
     }
 
-    pub fn print_active_by_remaining_time(&self) {
+    /// Prints the given entries in their order.
+    pub fn print_entries(&self, entries: &[Entry], layout: &Layout) {
+        let rows: Vec<(String, &Entry)> = entries.iter().map(|e| (e.name.clone(), e)).collect();
+        layout.print(self, &rows);
+    }
+
+    /// The active entries, and every group with the values of its most urgent member,
+    /// each with the name it is listed under.
+    fn active_by_remaining_time(&self) -> Vec<(String, &Entry)> {
         let mut active = self
             .entries
             .values()
             .filter(|e| !self.suspended_entries.contains(&e.name))
-            .collect::<Vec<&Entry>>();
-        active.sort_by(|a, b| a.remaining_time.cmp(&b.remaining_time));
-        for e in active {
-            // this is not nice, I should better make the case destinction here.
-            e.print(self.t1, self.t2, self.t3)
+            .map(|e| (e.name.clone(), e))
+            .collect::<Vec<(String, &Entry)>>();
+        for group in self.groups.values() {
+            if let Some(e) = self.most_urgent_member(group) {
+                active.push((format!("{} (group)", group.name), e));
+            }
         }
+        active.sort_by(|(a_name, a), (b_name, b)| {
+            (a.remaining_time, a_name).cmp(&(b.remaining_time, b_name))
+        });
+        active
     }
 
-    pub fn print_inactive_by_remaining_time(&self) {
+    fn inactive_by_remaining_time(&self) -> Vec<(String, &Entry)> {
         let mut inactive = self
             .entries
             .values()
             .filter(|e| self.suspended_entries.contains(&e.name))
-            .collect::<Vec<&Entry>>();
-        inactive.sort_by(|a, b| a.remaining_time.cmp(&b.remaining_time));
-        for e in inactive {
-            e.print_suspended()
-        }
+            .map(|e| (e.name.clone(), e))
+            .collect::<Vec<(String, &Entry)>>();
+        inactive.sort_by(|(a_name, a), (b_name, b)| {
+            (a.remaining_time, a_name).cmp(&(b.remaining_time, b_name))
+        });
+        inactive
     }
 }
 
@@ -226,84 +438,216 @@ pub struct EntryVec(Vec<Entry>);
 impl core::ops::Deref for EntryVec {
     type Target = Vec<Entry>;
 
-    fn deref(self: &'_ Self) -> &'_ Self::Target {
+    fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
 impl core::ops::DerefMut for EntryVec {
-    fn deref_mut(self: &'_ mut Self) -> &'_ mut Self::Target {
+    fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
+/// A field entries can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortField {
+    Name,
+    Remaining,
+    Last,
+    Interval,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl SortKey {
+    fn compare(&self, a: &Entry, b: &Entry) -> std::cmp::Ordering {
+        let ordering = match self.field {
+            SortField::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortField::Remaining => a.remaining_time.cmp(&b.remaining_time),
+            SortField::Last => a.last_contact.cmp(&b.last_contact),
+            SortField::Interval => a.interval.cmp(&b.interval),
+        };
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
 impl EntryVec {
+    pub fn new(entries: Vec<Entry>) -> Self {
+        EntryVec(entries)
+    }
+
+    /// Sorts by the first key, ties are broken by the following ones.
+    /// The sort is stable, so entries equal in every key keep their order.
+    pub fn sort_by_keys(&mut self, keys: &[SortKey]) {
+        self.sort_by(|a, b| {
+            keys.iter()
+                .map(|key| key.compare(a, b))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+
+    #[allow(dead_code)]
     pub fn sort_by_time_ascending(&mut self) {
-        self.sort_by(|a, b| a.remaining_time.cmp(&b.remaining_time));
+        self.sort_by_keys(&[SortKey {
+            field: SortField::Remaining,
+            descending: false,
+        }]);
     }
 
+    #[allow(dead_code)]
     pub fn sort_by_time_descending(&mut self) {
-        self.sort_by(|a, b| b.remaining_time.cmp(&a.remaining_time));
+        self.sort_by_keys(&[SortKey {
+            field: SortField::Remaining,
+            descending: true,
+        }]);
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// How urgently an entry needs attention, used for highlighting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Urgency {
+    Overdue,
+    Soon,
+    Normal,
+    Relaxed,
+    Suspended,
+}
+
+/// A single recorded contact with a person.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub date: DateTime<Utc>,
+    // Where the contact was recorded from, e.g. `manual` or `calendar`
+    pub channel: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entry {
     pub name: String,
     // The chat interval in days
     pub interval: usize,
     pub last_contact: DateTime<Utc>,
     remaining_time: i64,
+    // Alternative names used when matching imported data
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub emails: Vec<String>,
+    #[serde(default)]
+    pub phones: Vec<String>,
+    #[serde(default)]
+    pub birthday: Option<NaiveDate>,
+    #[serde(default)]
+    pub interactions: Vec<Interaction>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    // The entry isn't due before this date, even if the interval is over
+    #[serde(default)]
+    pub snoozed_until: Option<DateTime<Utc>>,
+}
+
+/// The days from `today` until `due`, negative once `due` has passed.
+pub fn days_until(due: NaiveDate, today: NaiveDate) -> i64 {
+    due.signed_duration_since(today).num_days()
 }
 
 impl Entry {
     pub fn new(name: String, interval: usize, last_contact: DateTime<Utc>) -> Self {
-        Entry {
+        let mut entry = Entry {
             name,
             interval,
             last_contact,
-            remaining_time: (interval as i64
-                - Utc::now().signed_duration_since(last_contact).num_days()),
+            remaining_time: 0,
+            aliases: Vec::new(),
+            emails: Vec::new(),
+            phones: Vec::new(),
+            birthday: None,
+            interactions: Vec::new(),
+            tags: Vec::new(),
+            notes: None,
+            snoozed_until: None,
+        };
+        entry.update_remaining_time();
+        entry
+    }
+
+    /// Adds an interaction to the history of the entry.
+    /// `last_contact` is only moved forward, never back.
+    /// Returns `false` if the same interaction was already recorded.
+    pub fn record_interaction(&mut self, interaction: Interaction) -> bool {
+        if self.interactions.contains(&interaction) {
+            return false;
+        }
+        if interaction.date > self.last_contact {
+            self.last_contact = interaction.date;
+            self.update_remaining_time();
+        }
+        self.interactions.push(interaction);
+        self.interactions.sort_by_key(|i| i.date);
+        true
+    }
+
+    pub fn remaining_time(&self) -> i64 {
+        self.remaining_time
+    }
+
+    /// Describes the remaining time in words, e.g. `overdue by 3 day(s)`.
+    pub fn remaining_description(&self) -> String {
+        match self.remaining_time {
+            r if r < 0 => format!("overdue by {} day(s)", -r),
+            0 => "due today".to_string(),
+            r => format!("due in {} day(s)", r),
         }
     }
 
     pub fn update_remaining_time(&mut self) {
-        self.remaining_time = self.interval as i64
-            - Utc::now()
-                .signed_duration_since(self.last_contact)
-                .num_days();
+        self.remaining_time = self.remaining_time_at(today());
     }
 
-    fn print(&self, t1: i64, t2: i64, t3: i64) {
-        let mut line = format!(
-            "{0: <15}  {1: <15}  {2: <15}  {3: <15}",
-            self.name,
-            self.remaining_time,
-            self.last_contact.date(),
-            self.interval,
-        );
-        if self.remaining_time < t1 {
-            line = line.red().on_black().to_string();
-        } else if self.remaining_time < t2 {
-            line = line.yellow().on_black().to_string();
-        } else if self.remaining_time > t3 {
-            line = line.green().on_black().to_string();
-        } else {
-            line = line.magenta().on_black().to_string();
+    /// The days left until the due date on `today`, negative once it has passed.
+    /// Counts calendar days, so an entry due tomorrow has one day left
+    /// regardless of the time of day of the last contact.
+    pub fn remaining_time_at(&self, today: NaiveDate) -> i64 {
+        days_until(self.due_date(), today)
+    }
+
+    /// The date the interval is counted from. This is the last contact,
+    /// unless a snooze pushes the due date further out.
+    pub fn counted_from(&self) -> DateTime<Utc> {
+        match self.snoozed_until {
+            Some(until) => std::cmp::max(
+                self.last_contact,
+                until - Duration::days(self.interval as i64),
+            ),
+            None => self.last_contact,
         }
-        println!("{}", line);
     }
 
-    fn print_suspended(&self) {
-        let mut line = format!(
-            "{0: <15}  {1: <15}  {2: <15}  {3: <15}",
-            self.name,
-            self.remaining_time,
-            self.last_contact.date(),
-            self.interval,
-        );
-        line = line.truecolor(211, 211, 211).on_black().to_string();
-        println!("{}", line);
+    /// The day the interval runs out.
+    pub fn due_date(&self) -> NaiveDate {
+        (self.counted_from() + Duration::days(self.interval as i64)).date_naive()
+    }
+
+    /// Postpones the entry to be due in `days` days from now, without recording a contact.
+    pub fn snooze(&mut self, days: i64) {
+        self.snoozed_until = Some(Utc::now() + Duration::days(days));
+        self.update_remaining_time();
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
     }
 }
 
@@ -318,12 +662,12 @@ mod tests {
         let e2 = Entry::new(
             "Daniel".to_string(),
             30,
-            Utc.ymd(2020, 3, 20).and_hms(12, 12, 12),
+            Utc.with_ymd_and_hms(2020, 3, 20, 12, 12, 12).unwrap(),
         );
         let e3 = Entry::new(
             "Baniel".to_string(),
             30,
-            Utc.ymd(2020, 5, 20).and_hms(12, 12, 12),
+            Utc.with_ymd_and_hms(2020, 5, 20, 12, 12, 12).unwrap(),
         );
         let mut entries = EntryVec(vec![e2, e1, e3]);
         entries.sort_by_time_descending();
@@ -339,12 +683,12 @@ mod tests {
         let e2 = Entry::new(
             "Daniel".to_string(),
             30,
-            Utc.ymd(2020, 3, 20).and_hms(12, 12, 12),
+            Utc.with_ymd_and_hms(2020, 3, 20, 12, 12, 12).unwrap(),
         );
         let e3 = Entry::new(
             "Baniel".to_string(),
             30,
-            Utc.ymd(2020, 5, 20).and_hms(12, 12, 12),
+            Utc.with_ymd_and_hms(2020, 5, 20, 12, 12, 12).unwrap(),
         );
         let mut entries = EntryVec(vec![e2, e1, e3]);
         entries.sort_by_time_ascending();
@@ -354,6 +698,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_record_interaction_keeps_newer_last_contact() {
+        let mut e1 = Entry::new("Martin".to_string(), 30, Utc::now());
+        let last = e1.last_contact;
+        let old = Interaction {
+            date: Utc.with_ymd_and_hms(2020, 3, 20, 12, 12, 12).unwrap(),
+            channel: "calendar".to_string(),
+        };
+        assert!(e1.record_interaction(old.clone()));
+        assert!(!e1.record_interaction(old));
+        assert_eq!(last, e1.last_contact);
+        assert_eq!(1, e1.interactions.len());
+    }
+
+    #[test]
+    fn test_rename_keeps_suspension() {
+        let mut table = Table::new();
+        table
+            .add_entry(Entry::new("Martin".to_string(), 30, Utc::now()))
+            .unwrap();
+        table.suspend_entry("Martin".to_string()).unwrap();
+        table
+            .rename_entry("Martin".to_string(), "Marty".to_string())
+            .unwrap();
+        assert!(table.suspended_entries.contains("Marty"));
+        assert_eq!("Marty", table.entries.get("Marty").unwrap().name);
+    }
+
     #[test]
     fn test_remaining_time() {
         let mut e1 = Entry::new("Martin".to_string(), 30, Utc::now());
@@ -363,14 +735,15 @@ mod tests {
 
     #[test]
     fn test_update_through_table_par() {
-        let remaining_daniel = 30 as i64
+        let remaining_daniel = 30_i64
             - Utc::now()
-                .signed_duration_since(Utc.ymd(2020, 3, 20).and_hms(12, 12, 12))
+                .date_naive()
+                .signed_duration_since(NaiveDate::from_ymd_opt(2020, 3, 20).unwrap())
                 .num_days();
         let e2 = Entry::new(
             "Daniel".to_string(),
             30,
-            Utc.ymd(2020, 3, 20).and_hms(12, 12, 12),
+            Utc.with_ymd_and_hms(2020, 3, 20, 12, 12, 12).unwrap(),
         );
         let mut table = Table::new();
         table.add_entry(e2).unwrap();
@@ -381,23 +754,96 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_due_entries_skips_suspended() {
+        let mut table = Table::new();
+        for (name, interval) in &[("Martin", 30), ("Daniel", 2), ("Thorben", 0)] {
+            table
+                .add_entry(Entry::new(name.to_string(), *interval, Utc::now()))
+                .unwrap();
+        }
+        table.suspend_entry("Thorben".to_string()).unwrap();
+        let due = table.due_entries(3);
+        assert_eq!(
+            vec!["Daniel"],
+            due.iter().map(|e| &e.name).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_groups() {
+        let mut table = Table::new();
+        for (name, interval) in &[("Anna", 3), ("Ben", 5), ("Carla", 1)] {
+            table
+                .add_entry(Entry::new(name.to_string(), *interval, Utc::now()))
+                .unwrap();
+        }
+        let band = |members: &[&str]| Group {
+            name: "Band".to_string(),
+            members: members.iter().map(|m| m.to_string()).collect(),
+        };
+        assert!(table.add_group(band(&["Anna", "Zoe"])).is_err());
+        assert!(table.groups.is_empty());
+        table.add_group(band(&["Anna", "Ben", "Carla"])).unwrap();
+        assert!(table.add_group(band(&[])).is_err());
+        assert!(table
+            .add_entry(Entry::new("Band".to_string(), 7, Utc::now()))
+            .is_err());
+        assert!(table
+            .rename_entry("Ben".to_string(), "Band".to_string())
+            .is_err());
+
+        table.suspend_entry("Carla".to_string()).unwrap();
+        let urgent = table.most_urgent_member(&table.groups["Band"]).unwrap();
+        assert_eq!(urgent.name, "Anna");
+
+        table
+            .rename_entry("Anna".to_string(), "Anne".to_string())
+            .unwrap();
+        table.remove_entry("Ben".to_string()).unwrap();
+        assert_eq!(table.groups["Band"].members, vec!["Anne", "Carla"]);
+        table.add_members("Band", &["Anne".to_string()]).unwrap();
+        table
+            .remove_members("Band", &["Carla".to_string()])
+            .unwrap();
+        assert_eq!(table.groups["Band"].members, vec!["Anne"]);
+        assert!(table
+            .remove_members("Band", &["Carla".to_string()])
+            .is_err());
+        assert!(table.remove_group("Choir".to_string()).is_err());
+        table.remove_group("Band".to_string()).unwrap();
+        assert!(table.groups.is_empty());
+    }
+
+    #[test]
+    fn test_snooze() {
+        let mut entry = Entry::new("Martin".to_string(), 7, Utc::now() - Duration::days(10));
+        entry.snooze(3);
+        assert_eq!(3, entry.remaining_time);
+        assert!(entry.interactions.is_empty());
+        // a later contact counts from the contact again
+        entry.last_contact = Utc::now();
+        entry.update_remaining_time();
+        assert_eq!(7, entry.remaining_time);
+    }
+
     #[test]
     fn test_print() {
         let e1 = Entry::new("Martin".to_string(), 30, Utc::now());
         let e2 = Entry::new(
             "Daniel".to_string(),
             30,
-            Utc.ymd(2020, 3, 20).and_hms(12, 12, 12),
+            Utc.with_ymd_and_hms(2020, 3, 20, 12, 12, 12).unwrap(),
         );
         let e3 = Entry::new(
             "Thorben".to_string(),
             35,
-            Utc.ymd(2020, 3, 20).and_hms(12, 12, 12),
+            Utc.with_ymd_and_hms(2020, 3, 20, 12, 12, 12).unwrap(),
         );
         let mut table = Table::new();
         table.add_entry(e2).unwrap();
         table.add_entry(e1).unwrap();
         table.add_entry(e3).unwrap();
-        table.print_all_by_remaining_time();
+        table.print_all_by_remaining_time(&Layout::default());
     }
 }
@@ -0,0 +1,499 @@
+//! A full-screen interface for the daily triage of the table.
+//!
+//! The state of the interface and its reaction to keys live in `App`,
+//! which knows nothing about the terminal. `run` connects it to one.
+
+use chrono::Utc;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Frame;
+use std::io;
+use std::path::PathBuf;
+
+use crate::hooks::{self, Hook};
+use crate::parse::parse_duration;
+use crate::table::{Entry, Interaction, Table, Urgency};
+
+const HELP: &str = "c contact  s snooze  p suspend/resume  i interval  t tag  q quit";
+
+/// A value that is currently typed in the footer.
+#[derive(Debug, PartialEq)]
+enum Prompt {
+    Snooze,
+    Interval,
+    Tag,
+}
+
+impl Prompt {
+    fn question(&self) -> &'static str {
+        match self {
+            Prompt::Snooze => "Snooze for (e.g. 3d, 2w): ",
+            Prompt::Interval => "New interval in days: ",
+            Prompt::Tag => "Only show tag (empty for all): ",
+        }
+    }
+}
+
+pub struct App {
+    table: Table,
+    // where changes are saved, nothing is written without it
+    path: Option<PathBuf>,
+    hooks: Vec<Hook>,
+    // the names of the listed entries, in order
+    names: Vec<String>,
+    state: ListState,
+    tag: Option<String>,
+    input: Option<(Prompt, String)>,
+    message: String,
+    pub quit: bool,
+    // set when hooks may have written to the terminal
+    pub dirty: bool,
+}
+
+impl App {
+    pub fn new(table: Table, path: Option<PathBuf>, hooks: Vec<Hook>) -> Self {
+        let mut app = App {
+            table,
+            path,
+            hooks,
+            names: Vec::new(),
+            state: ListState::default(),
+            tag: None,
+            input: None,
+            message: HELP.to_string(),
+            quit: false,
+            dirty: false,
+        };
+        app.refresh();
+        app
+    }
+
+    /// Lists the entries like `kit view`: active ones first, each by remaining time.
+    /// The selection stays on the same entry if it is still listed.
+    fn refresh(&mut self) {
+        let selected = self.selected().map(|e| e.name.clone());
+        let mut entries = self
+            .table
+            .entries
+            .values()
+            .filter(|e| self.tag.as_ref().is_none_or(|t| e.has_tag(t)))
+            .collect::<Vec<&Entry>>();
+        entries.sort_by(|a, b| {
+            let suspended = |e: &Entry| self.table.suspended_entries.contains(&e.name);
+            (suspended(a), a.remaining_time(), &a.name).cmp(&(
+                suspended(b),
+                b.remaining_time(),
+                &b.name,
+            ))
+        });
+        self.names = entries.iter().map(|e| e.name.clone()).collect();
+        let index = selected
+            .and_then(|s| self.names.iter().position(|n| *n == s))
+            .unwrap_or(0);
+        self.state.select(if self.names.is_empty() {
+            None
+        } else {
+            Some(index)
+        });
+    }
+
+    fn selected(&self) -> Option<&Entry> {
+        self.state
+            .selected()
+            .and_then(|i| self.names.get(i))
+            .map(|n| &self.table.entries[n])
+    }
+
+    fn save(&mut self, event: &str, interaction: Option<&Interaction>) {
+        if let Some(path) = &self.path {
+            self.table.to_json(path);
+        }
+        if let Some(entry) = self.selected() {
+            if !self.hooks.is_empty() {
+                hooks::run(&self.hooks, event, entry, interaction);
+                self.dirty = true;
+            }
+        }
+        self.refresh();
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if self.input.is_some() {
+            self.handle_input(key);
+            return;
+        }
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.state.select_previous(),
+            KeyCode::Home | KeyCode::Char('g') => self.state.select_first(),
+            KeyCode::End | KeyCode::Char('G') => self.state.select_last(),
+            KeyCode::Char('c') | KeyCode::Enter => self.log_contact(),
+            KeyCode::Char('p') => self.toggle_suspension(),
+            KeyCode::Char('s') if self.selected().is_some() => {
+                self.input = Some((Prompt::Snooze, String::new()))
+            }
+            KeyCode::Char('i') => {
+                if let Some(entry) = self.selected() {
+                    self.input = Some((Prompt::Interval, entry.interval.to_string()))
+                }
+            }
+            KeyCode::Char('t') => {
+                self.input = Some((Prompt::Tag, self.tag.clone().unwrap_or_default()))
+            }
+            _ => {}
+        }
+        // keep the selection in the list, `select_next` doesn't know its length
+        if let Some(i) = self.state.selected() {
+            if i >= self.names.len() {
+                self.state.select(self.names.len().checked_sub(1));
+            }
+        }
+    }
+
+    fn handle_input(&mut self, key: KeyCode) {
+        let (prompt, text) = self.input.as_mut().unwrap();
+        match key {
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Esc => {
+                self.input = None;
+                self.message = HELP.to_string();
+            }
+            KeyCode::Enter => {
+                let text = text.trim().to_string();
+                match prompt {
+                    Prompt::Snooze => self.snooze(&text),
+                    Prompt::Interval => self.set_interval(&text),
+                    Prompt::Tag => self.filter(&text),
+                }
+                self.input = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn log_contact(&mut self) {
+        let name = match self.selected() {
+            Some(entry) => entry.name.clone(),
+            None => return,
+        };
+        let interaction = Interaction {
+            date: Utc::now(),
+            channel: "manual".to_string(),
+        };
+        let entry = self.table.entries.get_mut(&name).unwrap();
+        entry.record_interaction(interaction.clone());
+        self.message = format!("Logged a contact with {}.", name);
+        self.save("interaction.logged", Some(&interaction));
+    }
+
+    fn toggle_suspension(&mut self) {
+        let name = match self.selected() {
+            Some(entry) => entry.name.clone(),
+            None => return,
+        };
+        let event = if self.table.suspended_entries.contains(&name) {
+            self.table.resume_entry(name.clone()).unwrap();
+            self.message = format!("Resumed {}.", name);
+            "entry.resumed"
+        } else {
+            self.table.suspend_entry(name.clone()).unwrap();
+            self.message = format!("Suspended {}.", name);
+            "entry.suspended"
+        };
+        self.save(event, None);
+    }
+
+    fn snooze(&mut self, raw: &str) {
+        let name = self.selected().unwrap().name.clone();
+        match parse_duration(raw) {
+            Ok(days) => {
+                self.table.entries.get_mut(&name).unwrap().snooze(days);
+                self.message = format!("Snoozed {} for {} day(s).", name, days);
+                self.save("entry.modified", None);
+            }
+            Err(e) => self.message = e.to_string(),
+        }
+    }
+
+    fn set_interval(&mut self, raw: &str) {
+        let name = self.selected().unwrap().name.clone();
+        match raw.parse::<usize>() {
+            Ok(interval) => {
+                let entry = self.table.entries.get_mut(&name).unwrap();
+                entry.interval = interval;
+                entry.update_remaining_time();
+                self.message = format!("Set the interval of {} to {} day(s).", name, interval);
+                self.save("entry.modified", None);
+            }
+            Err(_) => self.message = format!("{:?} is not a number of days.", raw),
+        }
+    }
+
+    fn filter(&mut self, tag: &str) {
+        self.tag = if tag.is_empty() {
+            None
+        } else {
+            Some(tag.to_string())
+        };
+        self.message = HELP.to_string();
+        self.refresh();
+    }
+
+    fn color(&self, entry: &Entry) -> Color {
+        match self.table.urgency(entry) {
+            Urgency::Overdue => Color::Red,
+            Urgency::Soon => Color::Yellow,
+            Urgency::Normal => Color::Magenta,
+            Urgency::Relaxed => Color::Green,
+            Urgency::Suspended => Color::DarkGray,
+        }
+    }
+
+    fn details(&self, entry: &Entry) -> Vec<Line<'_>> {
+        let bold = Style::new().add_modifier(Modifier::BOLD);
+        let mut lines = vec![
+            Line::styled(entry.name.clone(), bold),
+            Line::from(entry.remaining_description()),
+            Line::from(format!("Last contact: {}", entry.last_contact.date_naive())),
+            Line::from(format!("Interval: {} day(s)", entry.interval)),
+        ];
+        if let Some(until) = entry.snoozed_until.filter(|u| *u > Utc::now()) {
+            lines.push(Line::from(format!("Snoozed until: {}", until.date_naive())));
+        }
+        if self.table.suspended_entries.contains(&entry.name) {
+            lines.push(Line::from("Suspended"));
+        }
+        let lists = [
+            ("Tags", &entry.tags),
+            ("Aliases", &entry.aliases),
+            ("Emails", &entry.emails),
+            ("Phones", &entry.phones),
+        ];
+        for (label, list) in lists.iter().filter(|(_, l)| !l.is_empty()) {
+            lines.push(Line::from(format!("{}: {}", label, list.join(", "))));
+        }
+        if let Some(birthday) = entry.birthday {
+            lines.push(Line::from(format!("Birthday: {}", birthday)));
+        }
+        if let Some(notes) = &entry.notes {
+            lines.push(Line::default());
+            lines.push(Line::styled("Notes", bold));
+            lines.extend(notes.lines().map(|l| Line::from(l.to_string())));
+        }
+        lines.push(Line::default());
+        lines.push(Line::styled("History", bold));
+        if entry.interactions.is_empty() {
+            lines.push(Line::from("No recorded contacts."));
+        }
+        for interaction in entry.interactions.iter().rev() {
+            lines.push(Line::from(format!(
+                "{}  {}",
+                interaction.date.date_naive(),
+                interaction.channel
+            )));
+        }
+        lines
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, detail_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(main);
+
+        let width = self
+            .names
+            .iter()
+            .map(|n| n.chars().count())
+            .max()
+            .unwrap_or(0);
+        let items = self
+            .names
+            .iter()
+            .map(|n| {
+                let entry = &self.table.entries[n];
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{:<width$}  ", n, width = width)),
+                    Span::raw(format!("{:>5}", entry.remaining_time())),
+                ]))
+                .style(Style::new().fg(self.color(entry)))
+            })
+            .collect::<Vec<_>>();
+        let title = match &self.tag {
+            Some(tag) => format!(" kit [{}] ", tag),
+            None => " kit ".to_string(),
+        };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.state);
+
+        let details = match self.selected() {
+            Some(entry) => self.details(entry),
+            None => vec![Line::from("No entries.")],
+        };
+        frame.render_widget(
+            Paragraph::new(details)
+                .block(Block::default().borders(Borders::ALL))
+                .wrap(Wrap { trim: false }),
+            detail_area,
+        );
+
+        let status = match &self.input {
+            Some((prompt, text)) => format!("{}{}", prompt.question(), text),
+            None => self.message.clone(),
+        };
+        frame.render_widget(Paragraph::new(status), footer);
+    }
+}
+
+/// Runs the interface until the user quits.
+pub fn run(app: &mut App) -> io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let res = (|| {
+        while !app.quit {
+            if app.dirty {
+                terminal.clear()?;
+                app.dirty = false;
+            }
+            terminal.draw(|frame| app.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    app.handle_key(key.code);
+                }
+            }
+        }
+        Ok(())
+    })();
+    ratatui::try_restore()?;
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn app() -> App {
+        let mut table = Table::new();
+        for (name, interval, days_ago) in &[("Anna", 7, 10), ("Ben", 7, 1), ("Carl", 30, 2)] {
+            let last = Utc::now() - Duration::days(*days_ago);
+            table
+                .add_entry(Entry::new(name.to_string(), *interval, last))
+                .unwrap();
+        }
+        table.entries.get_mut("Carl").unwrap().tags = vec!["Family".to_string()];
+        App::new(table, None, Vec::new())
+    }
+
+    fn keys(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            let key = match c {
+                '\n' => KeyCode::Enter,
+                c => KeyCode::Char(c),
+            };
+            app.handle_key(key);
+        }
+    }
+
+    #[test]
+    fn test_sorted_like_view() {
+        let mut app = app();
+        app.table.suspend_entry("Anna".to_string()).unwrap();
+        app.refresh();
+        assert_eq!(app.names, vec!["Ben", "Carl", "Anna"]);
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut app = app();
+        assert_eq!(app.selected().unwrap().name, "Anna");
+        keys(&mut app, "jjjj");
+        assert_eq!(app.selected().unwrap().name, "Carl");
+        keys(&mut app, "k");
+        assert_eq!(app.selected().unwrap().name, "Ben");
+        keys(&mut app, "g");
+        assert_eq!(app.selected().unwrap().name, "Anna");
+    }
+
+    #[test]
+    fn test_log_contact() {
+        let mut app = app();
+        keys(&mut app, "c");
+        let anna = &app.table.entries["Anna"];
+        assert_eq!(anna.interactions.len(), 1);
+        assert_eq!(anna.remaining_time(), 7);
+        // the selection follows the entry to its new place
+        assert_eq!(app.selected().unwrap().name, "Anna");
+        assert_eq!(app.names, vec!["Ben", "Anna", "Carl"]);
+    }
+
+    #[test]
+    fn test_snooze() {
+        let mut app = app();
+        keys(&mut app, "s2w\n");
+        let anna = &app.table.entries["Anna"];
+        assert!(anna.interactions.is_empty());
+        assert_eq!(anna.remaining_time(), 14);
+        keys(&mut app, "sx\n");
+        assert_eq!(app.table.entries["Anna"].remaining_time(), 14);
+    }
+
+    #[test]
+    fn test_suspend_and_interval() {
+        let mut app = app();
+        keys(&mut app, "p");
+        assert!(app.table.suspended_entries.contains("Anna"));
+        keys(&mut app, "p");
+        assert!(!app.table.suspended_entries.contains("Anna"));
+        app.handle_key(KeyCode::Char('i'));
+        app.handle_key(KeyCode::Backspace);
+        keys(&mut app, "14\n");
+        assert_eq!(app.table.entries["Anna"].interval, 14);
+        assert_eq!(app.table.entries["Anna"].remaining_time(), 4);
+    }
+
+    #[test]
+    fn test_tag_filter() {
+        let mut app = app();
+        keys(&mut app, "tfamily\n");
+        assert_eq!(app.names, vec!["Carl"]);
+        assert_eq!(app.selected().unwrap().name, "Carl");
+        app.handle_key(KeyCode::Char('t'));
+        for _ in 0.."family".len() {
+            app.handle_key(KeyCode::Backspace);
+        }
+        keys(&mut app, "\n");
+        assert_eq!(app.names.len(), 3);
+    }
+
+    #[test]
+    fn test_draw() {
+        let mut app = app();
+        app.table.entries.get_mut("Anna").unwrap().notes = Some("Moved to Lisbon".to_string());
+        keys(&mut app, "c");
+        let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let screen = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect::<String>();
+        assert!(screen.contains("Carl"));
+        assert!(screen.contains("Moved to Lisbon"));
+        assert!(screen.contains("manual"));
+        assert!(screen.contains("Logged a contact with Anna."));
+    }
+}
@@ -0,0 +1,265 @@
+//! The questions `kit add` asks when it runs in a terminal without all details.
+
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::helpers::get_list;
+use crate::parse::{parse_date, parse_duration};
+use crate::table::{Entry, ExistingEntry, Table};
+
+/// Kinds of relationships with a suggested interval in days and a tag.
+pub const RELATIONSHIPS: &[(&str, usize, &str)] = &[
+    ("Close family", 7, "family"),
+    ("Close friend", 14, "friends"),
+    ("Family", 30, "family"),
+    ("Friend", 30, "friends"),
+    ("Colleague", 60, "work"),
+    ("Acquaintance", 90, ""),
+];
+
+/// The relationship suggested when the question is skipped.
+const DEFAULT_RELATIONSHIP: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct MissingDetails;
+
+impl fmt::Display for MissingDetails {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Name, interval and last chat are needed, \
+            or run `kit add` in a terminal to be asked for them."
+        )
+    }
+}
+
+impl error::Error for MissingDetails {
+    fn description(&self) -> &str {
+        "Missing details"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+/// What was already given on the command line.
+#[derive(Debug, Default)]
+pub struct Given<'a> {
+    pub name: Option<&'a str>,
+    pub interval: Option<usize>,
+    pub last_chat: Option<&'a str>,
+}
+
+/// Asks questions on `output` and reads the answers from `input`.
+pub struct Prompter<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Prompter { input, output }
+    }
+
+    /// Asks until `parse` accepts the answer. An empty answer is replaced by
+    /// the default, if there is one.
+    fn ask<T>(
+        &mut self,
+        question: &str,
+        default: Option<&str>,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> io::Result<T> {
+        loop {
+            match default {
+                Some(default) if !default.is_empty() => {
+                    write!(self.output, "{} [{}]: ", question, default)?
+                }
+                _ => write!(self.output, "{}: ", question)?,
+            }
+            self.output.flush()?;
+            let mut answer = String::new();
+            if self.input.read_line(&mut answer)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "The input ended before the entry was complete.",
+                ));
+            }
+            let answer = match (answer.trim(), default) {
+                ("", Some(default)) => default,
+                (answer, _) => answer,
+            };
+            match parse(answer) {
+                Ok(value) => return Ok(value),
+                Err(e) => writeln!(self.output, "{}", e)?,
+            }
+        }
+    }
+
+    /// Asks for everything that isn't given and returns the new entry,
+    /// or `None` if it shouldn't be added after all.
+    pub fn ask_entry(&mut self, table: &Table, given: Given) -> io::Result<Option<Entry>> {
+        let name = match given.name {
+            Some(name) => name.to_string(),
+            None => self.ask("Name", None, |answer| {
+                if answer.is_empty() {
+                    Err("The name can't be empty.".to_string())
+                } else if table.entries.contains_key(answer) {
+                    Err(ExistingEntry {
+                        name: answer.to_string(),
+                    }
+                    .to_string())
+                } else {
+                    Ok(answer.to_string())
+                }
+            })?,
+        };
+        let (interval, tag) = match given.interval {
+            Some(interval) => (interval, ""),
+            None => {
+                writeln!(self.output, "How close are you?")?;
+                for (i, (kind, days, _)) in RELATIONSHIPS.iter().enumerate() {
+                    writeln!(self.output, "  {}) {}, every {} days", i + 1, kind, days)?;
+                }
+                let default = (DEFAULT_RELATIONSHIP + 1).to_string();
+                let question = format!("Choose one of 1-{}", RELATIONSHIPS.len());
+                let (_, days, tag) = self.ask(&question, Some(&default), |answer| match answer
+                    .parse::<usize>()
+                {
+                    Ok(i) if i >= 1 && i <= RELATIONSHIPS.len() => Ok(RELATIONSHIPS[i - 1]),
+                    _ => Err(format!(
+                        "Please enter a number from 1 to {}.",
+                        RELATIONSHIPS.len()
+                    )),
+                })?;
+                let interval = self.ask(
+                    "Interval, in days or e.g. `3w`",
+                    Some(&days.to_string()),
+                    |answer| match parse_duration(answer) {
+                        Ok(days) if days > 0 => Ok(days as usize),
+                        Ok(_) => Err("The interval has to be at least a day.".to_string()),
+                        Err(e) => Err(e.to_string()),
+                    },
+                )?;
+                (interval, tag)
+            }
+        };
+        let last_contact = match given.last_chat {
+            Some(raw) => {
+                parse_date(raw).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+            }
+            None => self.ask(
+                "Last chat, e.g. `2024-3-1`, `yesterday` or `2w ago`",
+                Some("today"),
+                |answer| parse_date(answer).map_err(|e| e.to_string()),
+            )?,
+        };
+        let mut entry = Entry::new(name, interval, last_contact);
+        entry.tags = self.ask("Tags, comma separated", Some(tag), |answer| {
+            Ok(get_list(answer))
+        })?;
+        entry.emails = self.ask("Emails", Some(""), |answer| Ok(get_list(answer)))?;
+        entry.phones = self.ask("Phones", Some(""), |answer| Ok(get_list(answer)))?;
+        entry.birthday = self.ask("Birthday", Some(""), |answer| match answer {
+            "" => Ok(None),
+            raw => parse_date(raw)
+                .map(|date| Some(date.date_naive()))
+                .map_err(|e| e.to_string()),
+        })?;
+
+        writeln!(self.output)?;
+        writeln!(self.output, "  Name:      {}", entry.name)?;
+        writeln!(self.output, "  Interval:  every {} days", entry.interval)?;
+        writeln!(
+            self.output,
+            "  Last chat: {}",
+            entry.last_contact.format("%Y-%m-%d")
+        )?;
+        for (label, list) in &[
+            ("Tags:     ", &entry.tags),
+            ("Emails:   ", &entry.emails),
+            ("Phones:   ", &entry.phones),
+        ] {
+            if !list.is_empty() {
+                writeln!(self.output, "  {} {}", label, list.join(", "))?;
+            }
+        }
+        if let Some(birthday) = entry.birthday {
+            writeln!(self.output, "  Birthday:  {}", birthday.format("%Y-%m-%d"))?;
+        }
+        let add = self.ask("Add this entry? [Y/n]", Some(""), |answer| {
+            match answer.to_lowercase().as_str() {
+                "" | "y" | "yes" => Ok(true),
+                "n" | "no" => Ok(false),
+                _ => Err("Please answer y or n.".to_string()),
+            }
+        })?;
+        Ok(if add { Some(entry) } else { None })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, Utc};
+
+    fn ask(table: &Table, given: Given, answers: &str) -> (io::Result<Option<Entry>>, String) {
+        let mut output = Vec::new();
+        let entry = Prompter::new(answers.as_bytes(), &mut output).ask_entry(table, given);
+        (entry, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_defaults() {
+        let (entry, output) = ask(&Table::new(), Given::default(), "Anna\n\n\n\n\n\n\n\n\n");
+        let entry = entry.unwrap().unwrap();
+        assert_eq!(entry.name, "Anna");
+        assert_eq!(entry.interval, 30);
+        assert_eq!(entry.tags, vec!["friends"]);
+        assert_eq!(entry.last_contact.date_naive(), Utc::now().date_naive());
+        assert!(entry.emails.is_empty());
+        assert!(output.contains("  Interval:  every 30 days\n"));
+    }
+
+    #[test]
+    fn test_answers() {
+        let mut table = Table::new();
+        table
+            .add_entry(Entry::new("Ben".to_string(), 7, Utc::now()))
+            .unwrap();
+        let answers =
+            "Ben\nBenny\n9\n1\n2w\n3d ago\nfamily, school\nb@example.com\n\n1990-5-1\nmaybe\ny\n";
+        let (entry, output) = ask(&table, Given::default(), answers);
+        let entry = entry.unwrap().unwrap();
+        assert!(output.contains(r#"Name "Ben" is already used."#));
+        assert!(output.contains("Please enter a number from 1 to 6."));
+        assert_eq!(entry.name, "Benny");
+        assert_eq!(entry.interval, 14);
+        assert_eq!(
+            Utc::now()
+                .signed_duration_since(entry.last_contact)
+                .num_days(),
+            3
+        );
+        assert_eq!(entry.tags, vec!["family", "school"]);
+        assert_eq!(entry.emails, vec!["b@example.com"]);
+        assert_eq!(entry.birthday, NaiveDate::from_ymd_opt(1990, 5, 1));
+    }
+
+    #[test]
+    fn test_given_and_declined() {
+        let given = Given {
+            name: Some("Carl"),
+            interval: Some(5),
+            last_chat: None,
+        };
+        let (entry, output) = ask(&Table::new(), given, "yesterday\n\n\n\n\nn\n");
+        assert!(entry.unwrap().is_none());
+        assert!(output.starts_with("Last chat"));
+        assert!(!output.contains("How close"));
+        let (entry, _) = ask(&Table::new(), Given::default(), "Carl\n");
+        assert_eq!(entry.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -5,10 +5,27 @@ use std::process::Command;
 #[test]
 fn whole_workflow() -> Result<(), Box<dyn std::error::Error>> {
     let mut cmd = Command::cargo_bin("kit")?;
-    cmd.arg("add").arg("X").arg("30").arg("2020-1-1").arg("-n");
+    cmd.arg("add").arg("X").arg("30").arg("2020-1-1");
     // cmd.arg("add").arg("X").arg("30").arg("now");
     cmd.assert().stderr(predicate::str::contains("Added \"X\""));
 
+    let calendar =
+        std::env::temp_dir().join(format!("kit-test-calendar-{}.ics", std::process::id()));
+    std::fs::write(
+        &calendar,
+        "BEGIN:VEVENT\nDTSTART:20200105T120000Z\nSUMMARY:Lunch with X\nEND:VEVENT\n",
+    )?;
+    let mut cmd = Command::cargo_bin("kit")?;
+    cmd.arg("sync").arg("ics").arg(&calendar);
+    cmd.assert()
+        .stderr(predicate::str::contains("Recorded 1 new interaction(s)"));
+
+    let mut cmd = Command::cargo_bin("kit")?;
+    cmd.arg("check");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("X: overdue by"));
+
     let mut cmd = Command::cargo_bin("kit")?;
     cmd.arg("modify").arg("X").arg("interval").arg("10");
     cmd.assert()
@@ -19,6 +36,16 @@ fn whole_workflow() -> Result<(), Box<dyn std::error::Error>> {
     cmd.assert()
         .stderr(predicate::str::contains("Modified \"X\""));
 
+    let mut cmd = Command::cargo_bin("kit")?;
+    cmd.arg("check").arg("--overdue");
+    cmd.assert().code(0).stdout(predicate::str::is_empty());
+
+    let mut cmd = Command::cargo_bin("kit")?;
+    cmd.arg("check").arg("--due-within").arg("2w");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("X: due in"));
+
     let mut cmd = Command::cargo_bin("kit")?;
     cmd.arg("modify").arg("X").arg("last").arg("2015-10-10");
     cmd.assert()